@@ -0,0 +1,296 @@
+use crate::error::{ErrorKind, ToolError};
+use serde_json::{Map, Value};
+
+/// Largest edit distance at which an unknown argument is considered a typo of a valid one
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Check tool arguments against the tool's declared input schema.
+///
+/// Rejects unknown keys (suggesting the closest valid one), missing required keys and
+/// values of the wrong type. Obviously compatible values are coerced in place first:
+/// numbers and booleans given as strings and vice versa, and a single value where an
+/// array is expected. `null` is left alone and treated by the tools as absent.
+pub fn validate_args(
+    schema: &Map<String, Value>,
+    args: &mut Map<String, Value>,
+) -> Result<(), ToolError> {
+    let empty = Map::new();
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+
+    let mut unknown: Vec<&String> = args
+        .keys()
+        .filter(|k| !properties.contains_key(*k))
+        .collect();
+    unknown.sort();
+    if let Some(key) = unknown.first() {
+        let mut valid: Vec<&str> = properties.keys().map(String::as_str).collect();
+        valid.sort();
+        let mut message = format!("Unknown argument '{}'.", key);
+        if let Some(suggestion) = closest_key(key, &valid) {
+            message.push_str(&format!(" Did you mean '{}'?", suggestion));
+        }
+        message.push_str(&format!(" Valid arguments: {}", valid.join(", ")));
+        return Err(ToolError::new(ErrorKind::InvalidArgument, message));
+    }
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for key in required.iter().filter_map(Value::as_str) {
+            if args.get(key).is_none_or(Value::is_null) {
+                return Err(ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("Missing required argument '{}'", key),
+                ));
+            }
+        }
+    }
+
+    for (key, value) in args.iter_mut() {
+        if value.is_null() {
+            continue;
+        }
+        let property = &properties[key];
+        *value = coerce(value.take(), property).map_err(|value| {
+            ToolError::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Argument '{}' must be {}, got {}",
+                    key,
+                    describe_type(property),
+                    value
+                ),
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Convert `value` to the schema's type if it is compatible, returning it unchanged as the error otherwise
+fn coerce(value: Value, schema: &Value) -> Result<Value, Value> {
+    let Some(expected) = schema.get("type").and_then(Value::as_str) else {
+        return Ok(value);
+    };
+
+    match (expected, value) {
+        ("string", Value::String(s)) => Ok(Value::String(s)),
+        ("string", Value::Number(n)) => Ok(Value::String(n.to_string())),
+        ("string", Value::Bool(b)) => Ok(Value::String(b.to_string())),
+        ("integer", Value::Number(n)) if n.is_i64() || n.is_u64() => Ok(Value::Number(n)),
+        ("integer", Value::Number(n)) => match n.as_f64() {
+            Some(f) if f.fract() == 0.0 => Ok(Value::from(f as i64)),
+            _ => Err(Value::Number(n)),
+        },
+        ("integer", Value::String(s)) => match s.trim().parse::<i64>() {
+            Ok(i) => Ok(Value::from(i)),
+            Err(_) => Err(Value::String(s)),
+        },
+        ("number", Value::Number(n)) => Ok(Value::Number(n)),
+        ("number", Value::String(s)) => match s.trim().parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(Value::from(f)),
+            _ => Err(Value::String(s)),
+        },
+        ("boolean", Value::Bool(b)) => Ok(Value::Bool(b)),
+        ("boolean", Value::String(s)) => match s.trim().to_lowercase().as_str() {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err(Value::String(s)),
+        },
+        ("array", value) => {
+            let items = match value {
+                Value::Array(items) => items,
+                single => vec![single],
+            };
+            let item_schema = schema.get("items").unwrap_or(&Value::Null);
+            let coerced: Vec<Result<Value, Value>> = items
+                .into_iter()
+                .map(|item| coerce(item, item_schema))
+                .collect();
+            if coerced.iter().all(Result::is_ok) {
+                Ok(Value::Array(coerced.into_iter().flatten().collect()))
+            } else {
+                Err(Value::Array(
+                    coerced
+                        .into_iter()
+                        .map(|r| r.unwrap_or_else(|v| v))
+                        .collect(),
+                ))
+            }
+        }
+        ("object", Value::Object(o)) => Ok(Value::Object(o)),
+        (_, value) => Err(value),
+    }
+}
+
+/// Human-readable form of a property's type, e.g. "an array of strings"
+fn describe_type(schema: &Value) -> String {
+    match schema.get("type").and_then(Value::as_str) {
+        Some("array") => match schema["items"].get("type").and_then(Value::as_str) {
+            Some("string") => "an array of strings".to_string(),
+            Some("integer") => "an array of integers".to_string(),
+            Some(other) => format!("an array of {} values", other),
+            None => "an array".to_string(),
+        },
+        Some("integer") => "an integer".to_string(),
+        Some("object") => "an object".to_string(),
+        Some(other) => format!("a {}", other),
+        None => "a valid value".to_string(),
+    }
+}
+
+/// Valid key most likely meant by `key`: a close edit distance, or one containing the other
+fn closest_key<'a>(key: &str, valid: &[&'a str]) -> Option<&'a str> {
+    let key = key.to_lowercase();
+    valid
+        .iter()
+        .map(|candidate| (levenshtein(&key, candidate), *candidate))
+        .filter(|(distance, candidate)| {
+            *distance <= MAX_SUGGESTION_DISTANCE
+                || candidate.contains(key.as_str())
+                || key.contains(candidate)
+        })
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Edit distance between two strings, counted in characters
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Map<String, Value> {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string" },
+                "engines": { "type": "array", "items": { "type": "string" } },
+                "max_chars": { "type": "integer" },
+                "readability": { "type": "boolean" },
+                "cursor": { "type": "string" },
+            },
+            "required": ["query"],
+        })
+        .as_object()
+        .unwrap()
+        .clone()
+    }
+
+    fn args(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_validate_args_suggests_closest_key() {
+        let error = validate_args(&schema(), &mut args(json!({ "q": "rust" }))).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InvalidArgument);
+        assert!(
+            error
+                .message
+                .starts_with("Unknown argument 'q'. Did you mean 'query'?")
+        );
+        assert!(
+            error
+                .message
+                .ends_with("Valid arguments: cursor, engines, max_chars, query, readability")
+        );
+
+        let error =
+            validate_args(&schema(), &mut args(json!({ "search_query": "rust" }))).unwrap_err();
+        assert!(error.message.contains("Did you mean 'query'?"));
+
+        let error = validate_args(
+            &schema(),
+            &mut args(json!({ "query": "rust", "engnes": [] })),
+        )
+        .unwrap_err();
+        assert!(error.message.contains("Did you mean 'engines'?"));
+
+        let error = validate_args(
+            &schema(),
+            &mut args(json!({ "query": "rust", "language": "en" })),
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .message
+                .starts_with("Unknown argument 'language'. Valid")
+        );
+    }
+
+    #[test]
+    fn test_validate_args_coerces_compatible_types() {
+        let mut input = args(json!({
+            "query": 42,
+            "engines": "wikipedia",
+            "max_chars": "500",
+            "readability": "true",
+            "cursor": null,
+        }));
+        validate_args(&schema(), &mut input).unwrap();
+        assert_eq!(
+            Value::Object(input),
+            json!({
+                "query": "42",
+                "engines": ["wikipedia"],
+                "max_chars": 500,
+                "readability": true,
+                "cursor": null,
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_args_rejects_wrong_types() {
+        let error = validate_args(
+            &schema(),
+            &mut args(json!({ "query": "rust", "max_chars": "lots" })),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.message,
+            "Argument 'max_chars' must be an integer, got \"lots\""
+        );
+
+        let error = validate_args(
+            &schema(),
+            &mut args(json!({ "query": "rust", "engines": [{ "name": "bing" }] })),
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .message
+                .starts_with("Argument 'engines' must be an array of strings")
+        );
+
+        let error =
+            validate_args(&schema(), &mut args(json!({ "engines": ["bing"] }))).unwrap_err();
+        assert_eq!(error.message, "Missing required argument 'query'");
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("engnes", "engines"), 1);
+        assert_eq!(levenshtein("café", "cafe"), 1);
+    }
+}
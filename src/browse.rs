@@ -1,84 +1,1855 @@
-use anyhow::{Result, anyhow};
-use extism_pdk::{HttpRequest, config, http, info};
-use html2md;
+use crate::error::{ErrorKind, ToolError};
+use crate::feed::{declares_feed, feed_markdown, is_feed, parse_feed};
+use crate::readability::extract_main_content;
+use crate::searxng::{parse_retry_after, unix_now};
+use crate::trace::{info, warn};
+use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use encoding_rs::{Encoding, UTF_8};
+use extism_pdk::{HttpRequest, config, http};
+use quick_xml::Reader;
+use quick_xml::events::Event;
 use regex::Regex;
-use url::Url;
+use scraper::{ElementRef, Html, Selector};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::sync::LazyLock;
+use url::{Host, Url};
+
+/// Image found on a browsed page
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ImageEntry {
+    pub src: String,
+    pub alt: Option<String>,
+    pub width: Option<String>,
+    pub height: Option<String>,
+}
+
+// Match <style>...</style> and <script>...</script> tags (case insensitive, with attributes,
+// dot matches newlines), compiled once rather than on every browse
+static STYLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<style[^>]*>.*?</style>").unwrap());
+static SCRIPT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<script[^>]*>.*?</script>").unwrap());
 
 /// Strip <style> and <script> elements from HTML
 fn strip_styles_and_scripts(html: &str) -> String {
-    // Regex to match <style>...</style> and <script>...</script> tags (case insensitive, with attributes, dot matches newlines)
-    let style_re = Regex::new(r"(?is)<style[^>]*>.*?</style>").unwrap();
-    let script_re = Regex::new(r"(?is)<script[^>]*>.*?</script>").unwrap();
-
     // Remove style and script tags
-    let without_styles = style_re.replace_all(html, "");
-    let cleaned_html = script_re.replace_all(&without_styles, "");
+    let without_styles = STYLE_RE.replace_all(html, "");
+    let cleaned_html = SCRIPT_RE.replace_all(&without_styles, "");
 
     cleaned_html.to_string()
 }
 
-pub fn browse(url: &str) -> Result<String> {
-    let follow_redirects_str = config::get("BROWSE_FOLLOW_REDIRECTS")
+// Matches HTML comments, conditional `<!--[if IE]>...<![endif]-->` blocks included
+static COMMENT_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<!--.*?-->").unwrap());
+
+/// Strip HTML comments, whose debugging notes or CMS metadata would otherwise leak into
+/// the Markdown
+fn strip_html_comments(html: &str) -> String {
+    COMMENT_RE.replace_all(html, "").into_owned()
+}
+
+/// Remove site navigation: every `<nav>`, plus `<header>` and `<footer>` elements that
+/// belong to the page rather than to an article or section (whose header holds its title)
+fn strip_landmarks(html: &str) -> String {
+    let mut document = Html::parse_document(html);
+    let landmarks = Selector::parse("nav, header, footer").unwrap();
+
+    let ids: Vec<_> = document
+        .select(&landmarks)
+        .filter(|element| {
+            element.value().name() == "nav"
+                || !element.ancestors().filter_map(ElementRef::wrap).any(|a| {
+                    matches!(
+                        a.value().name(),
+                        "article" | "aside" | "main" | "nav" | "section"
+                    )
+                })
+        })
+        .map(|element| element.id())
+        .collect();
+
+    for id in ids {
+        if let Some(mut node) = document.tree.get_mut(id) {
+            node.detach();
+        }
+    }
+
+    document.html()
+}
+
+/// Class prefixes naming the language of a code block, most specific first
+const CODE_LANGUAGE_PREFIXES: &[&str] = &["language-", "lang-", "highlight-source-", "highlight-"];
+
+/// Language named by one of the element's classes, e.g. `language-rust`
+fn class_language(element: ElementRef) -> Option<String> {
+    element.value().classes().find_map(|class| {
+        CODE_LANGUAGE_PREFIXES
+            .iter()
+            .find_map(|prefix| class.strip_prefix(prefix))
+            .filter(|language| !language.is_empty())
+            .map(str::to_lowercase)
+    })
+}
+
+/// Language of every `<pre>` block in document order, taken from the block itself,
+/// its `<code>` element or a wrapping element
+fn code_block_languages(html: &str) -> Vec<Option<String>> {
+    let document = Html::parse_document(html);
+    let pre = Selector::parse("pre").unwrap();
+    let code = Selector::parse("code").unwrap();
+
+    document
+        .select(&pre)
+        .map(|block| {
+            class_language(block)
+                .or_else(|| block.select(&code).next().and_then(class_language))
+                .or_else(|| {
+                    block
+                        .parent()
+                        .and_then(ElementRef::wrap)
+                        .and_then(class_language)
+                })
+        })
+        .collect()
+}
+
+/// Convert HTML to Markdown, restoring the code block language hints html2md drops
+fn html_to_markdown(html: &str) -> String {
+    let markdown = html2md::parse_html(html);
+    let mut languages = code_block_languages(html).into_iter();
+    let mut in_block = false;
+
+    let lines: Vec<String> = markdown
+        .split('\n')
+        .map(|line| {
+            if !line.trim_start().starts_with("```") {
+                return line.to_string();
+            }
+            in_block = !in_block;
+            if in_block
+                && let Some(language) = languages.next().flatten()
+                && line.trim() == "```"
+            {
+                return format!("{}{}", line, language);
+            }
+            line.to_string()
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+/// Elements that stand as their own paragraph in plain text
+const TEXT_BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "dl",
+    "div",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "ul",
+];
+
+/// Elements that start a new line in plain text
+const TEXT_LINE_ELEMENTS: &[&str] = &["br", "dd", "dt", "li", "tr"];
+
+/// Append an element's text, with source whitespace collapsed and line or paragraph breaks
+/// around block elements
+fn push_element_text(element: ElementRef, text: &mut String) {
+    for child in element.children() {
+        if let Some(child_element) = ElementRef::wrap(child) {
+            let name = child_element.value().name();
+            let block = TEXT_BLOCK_ELEMENTS.contains(&name);
+            if block {
+                text.push_str("\n\n");
+            } else if TEXT_LINE_ELEMENTS.contains(&name) {
+                text.push('\n');
+            }
+            push_element_text(child_element, text);
+            if block {
+                text.push_str("\n\n");
+            } else if matches!(name, "td" | "th") {
+                text.push(' ');
+            }
+        } else if let Some(node_text) = child.value().as_text() {
+            if node_text.starts_with(char::is_whitespace) {
+                text.push(' ');
+            }
+            let words = node_text.split_whitespace().collect::<Vec<_>>().join(" ");
+            text.push_str(&words);
+            if !words.is_empty() && node_text.ends_with(char::is_whitespace) {
+                text.push(' ');
+            }
+        }
+    }
+}
+
+/// Convert HTML to readable plain text: all tags dropped, paragraphs separated by blank lines
+fn html_to_text(html: &str) -> String {
+    let document = Html::parse_document(html);
+    let body = Selector::parse("body").unwrap();
+    let root = document
+        .select(&body)
+        .next()
+        .unwrap_or_else(|| document.root_element());
+
+    let mut text = String::new();
+    push_element_text(root, &mut text);
+    let lines: Vec<&str> = text.lines().map(str::trim).collect();
+    collapse_blank_lines(&lines.join("\n"))
+}
+
+// Two or more blank lines in a row, counting lines of only spaces or tabs as blank
+static BLANK_LINES_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\n(?:[ \t]*\n){2,}").unwrap());
+
+/// Collapse runs of blank lines left by stripped elements into one and trim the ends
+fn collapse_blank_lines(markdown: &str) -> String {
+    BLANK_LINES_RE
+        .replace_all(markdown, "\n\n")
+        .trim()
+        .to_string()
+}
+
+/// Parse an ATX heading such as `## Title` or `### Title ###` into its level and text
+fn atx_heading(line: &str) -> Option<(usize, String)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+
+    // A closing run of `#` only counts when separated by a space, so `C#` survives
+    let text = rest.trim();
+    let without_closing = text.trim_end_matches('#');
+    let text = if without_closing.is_empty() || without_closing.ends_with(' ') {
+        without_closing.trim_end()
+    } else {
+        text
+    };
+    (!text.is_empty()).then(|| (level, text.to_string()))
+}
+
+/// Headings of a Markdown document as (level, text). Both ATX and setext headings are
+/// recognised, since html2md writes H1 and H2 in setext style.
+fn markdown_headings(markdown: &str) -> Vec<(usize, String)> {
+    let mut headings = Vec::new();
+    let mut in_code_block = false;
+    let mut previous: Option<&str> = None;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            previous = None;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+
+        let underline = !trimmed.is_empty()
+            && (trimmed.chars().all(|c| c == '=') || trimmed.chars().all(|c| c == '-'));
+        if underline && let Some(text) = previous.take() {
+            let level = if trimmed.starts_with('=') { 1 } else { 2 };
+            headings.push((level, text.to_string()));
+            continue;
+        }
+
+        if let Some(heading) = atx_heading(trimmed) {
+            headings.push(heading);
+            previous = None;
+        } else {
+            // Only a plain paragraph line can be the text of a setext heading
+            let is_block_syntax = trimmed.starts_with(['-', '*', '+', '>', '|']);
+            previous = (!trimmed.is_empty() && !is_block_syntax).then_some(trimmed);
+        }
+    }
+
+    headings
+}
+
+/// GitHub-style anchor for a heading: lowercase, punctuation dropped, spaces as hyphens
+fn heading_anchor(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Build a nested bullet list linking to every heading of the Markdown document.
+///
+/// Nesting is relative to the highest heading level present; repeated headings get
+/// `-1`, `-2`, ... suffixes on their anchors. Returns an empty string without headings.
+fn generate_toc(markdown: &str) -> String {
+    let headings = markdown_headings(markdown);
+    let Some(top_level) = headings.iter().map(|(level, _)| *level).min() else {
+        return String::new();
+    };
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut toc = String::new();
+    for (level, text) in headings {
+        let anchor = heading_anchor(&text);
+        let count = seen.entry(anchor.clone()).or_insert(0);
+        let anchor = if *count == 0 {
+            anchor
+        } else {
+            format!("{}-{}", anchor, count)
+        };
+        *count += 1;
+
+        toc.push_str(&format!(
+            "{}- [{}](#{})\n",
+            "  ".repeat(level - top_level),
+            text,
+            anchor
+        ));
+    }
+
+    toc
+}
+
+/// Whether a boolean config key is set to `true`
+fn config_flag(key: &str) -> bool {
+    config::get(key)
+        .ok()
+        .flatten()
+        .map(|s| s == "true")
+        .unwrap_or(false)
+}
+
+// An opening or closing `<a>` tag
+static ANCHOR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)</?a\b[^>]*>").unwrap());
+
+/// Remove `<img>` tags so no image Markdown is emitted
+fn strip_images(html: &str) -> String {
+    IMG_RE.replace_all(html, "").to_string()
+}
+
+/// Remove `<a>` tags but keep their content, turning links into plain text
+fn strip_links(html: &str) -> String {
+    ANCHOR_RE.replace_all(html, "").to_string()
+}
+
+/// Open Graph metadata from a page's `<meta property="og:*">` tags
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct OpenGraph {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub og_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl OpenGraph {
+    pub fn is_empty(&self) -> bool {
+        *self == OpenGraph::default()
+    }
+}
+
+/// Average reading speed used for `reading_time_seconds`
+const WORDS_PER_MINUTE: u32 = 200;
+
+/// Size of the converted page, to judge whether it is worth reading in full, and the
+/// response it was converted from
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PageMetadata {
+    pub word_count: u32,
+    pub reading_time_seconds: u32,
+    /// URL the page was finally served from, after any redirects
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<String>,
+    /// Redirects followed to reach the page, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<RedirectHop>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// Size of the response body as fetched, before conversion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<usize>,
+    /// Text of the page's `<title>`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+impl PageMetadata {
+    pub fn from_markdown(markdown: &str) -> Self {
+        // Tokens without any letters or digits are Markdown syntax (`#`, `*`, `---`, ...)
+        let word_count = markdown
+            .split_whitespace()
+            .filter(|token| token.chars().any(char::is_alphanumeric))
+            .count() as u32;
+        Self {
+            word_count,
+            reading_time_seconds: word_count * 60 / WORDS_PER_MINUTE,
+            final_url: None,
+            redirects: Vec::new(),
+            status: None,
+            content_type: None,
+            size_bytes: None,
+            title: None,
+        }
+    }
+
+    /// Describe the final response the page came from and how it was reached
+    fn with_response(mut self, page: &FetchResponse, size_bytes: usize) -> Self {
+        self.final_url = Some(page.url.clone());
+        self.redirects = page.redirects.clone();
+        self.status = Some(page.status);
+        self.content_type = page.content_type().map(str::to_string);
+        self.size_bytes = Some(size_bytes);
+        self
+    }
+}
+
+/// Text of a page's `<title>`, with whitespace collapsed
+fn extract_title(html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    let title = Selector::parse("title").unwrap();
+    document
+        .select(&title)
+        .next()
+        .map(|element| {
+            element
+                .text()
+                .collect::<String>()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|title| !title.is_empty())
+}
+
+/// Result of browsing a page
+#[derive(Debug)]
+pub struct BrowseOutput {
+    /// Page text: Markdown converted from HTML, or the body itself when that reads better
+    pub markdown: String,
+    /// Format of `markdown`: `text/markdown`, `text/html`, `text/plain` or `application/json`
+    pub mime_type: &'static str,
+    pub metadata: PageMetadata,
+    pub open_graph: OpenGraph,
+    /// Parsed `application/ld+json` blocks
+    pub json_ld: Vec<serde_json::Value>,
+    /// Raw body when the content is binary; `markdown` then only describes it
+    pub binary: Option<Vec<u8>>,
+}
+
+/// Class/id fragments used by publishers to wrap gated content
+const PAYWALL_MARKERS: &[&str] = &[
+    "paywall",
+    "premium-content",
+    "subscriber-only",
+    "subscribers-only",
+    "meteredcontent",
+    "piano-offer",
+];
+
+/// Share of the body text hidden with `display:none` above which the page is treated as gated
+const HIDDEN_TEXT_RATIO: f64 = 0.5;
+
+/// Returned by `browse` when the page appears to be behind a paywall
+#[derive(Debug)]
+pub struct PaywallDetected;
+
+impl std::fmt::Display for PaywallDetected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Paywall detected: content may be incomplete")
+    }
+}
+
+impl std::error::Error for PaywallDetected {}
+
+/// What `browse` turns an HTML page into
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum PageFormat {
+    #[default]
+    Markdown,
+    /// The cleaned HTML itself, for markup that Markdown loses
+    Html,
+    /// Plain text with paragraph breaks
+    Text,
+}
+
+impl PageFormat {
+    pub const NAMES: &'static [&'static str] = &["markdown", "html", "text"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "markdown" => Some(Self::Markdown),
+            "html" => Some(Self::Html),
+            "text" => Some(Self::Text),
+            _ => None,
+        }
+    }
+}
+
+/// Per-call browse options
+#[derive(Debug, Default)]
+pub struct BrowseOptions {
+    /// CSS selectors whose matches, taken selector by selector, replace the page
+    pub selectors: Vec<String>,
+    /// Format of HTML pages; other content types keep their own rendering
+    pub format: PageFormat,
+    /// Overrides `BROWSE_READABILITY` when set
+    pub readability: Option<bool>,
+    /// Prepend a table of contents built from the page's headings
+    pub generate_toc: bool,
+    pub fetch: FetchOptions,
+}
+
+/// Per-call overrides of how a page is fetched
+#[derive(Debug, Default, Clone)]
+pub struct FetchOptions {
+    /// Overrides `BROWSE_USER_AGENT` when set
+    pub user_agent: Option<String>,
+    /// Overrides `BROWSE_FOLLOW_REDIRECTS` when set
+    pub follow_redirects: Option<bool>,
+    /// Overrides `BROWSE_MAX_REDIRECTS` when set
+    pub max_redirects: Option<usize>,
+}
+
+// A `name=value` attribute with a double-quoted, single-quoted or bare value
+static ATTR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?is)([a-z_:][a-z0-9_:.-]*)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+});
+// An `<img>` tag, capturing its attributes
+static IMG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?is)<img\b([^>]*)>").unwrap());
+
+/// Parse the attributes of a single HTML tag into (lowercased name, value) pairs
+fn parse_attributes(attrs: &str) -> Vec<(String, String)> {
+    ATTR_RE
+        .captures_iter(attrs)
+        .map(|caps| {
+            let name = caps[1].to_lowercase();
+            let value = caps
+                .get(2)
+                .or_else(|| caps.get(3))
+                .or_else(|| caps.get(4))
+                .map(|m| m.as_str().to_string())
+                .unwrap_or_default();
+            (name, value)
+        })
+        .collect()
+}
+
+/// Extract every <img> tag with a non-empty src from HTML
+pub fn extract_images(html: &str) -> Vec<ImageEntry> {
+    IMG_RE
+        .captures_iter(html)
+        .filter_map(|caps| {
+            let attrs = parse_attributes(&caps[1]);
+            let get = |name: &str| {
+                attrs
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, v)| v.clone())
+            };
+
+            let src = get("src").filter(|s| !s.trim().is_empty())?;
+            Some(ImageEntry {
+                src: src.trim().to_string(),
+                alt: get("alt"),
+                width: get("width"),
+                height: get("height"),
+            })
+        })
+        .collect()
+}
+
+/// Hyperlink found on a browsed page
+#[derive(Debug, Serialize, PartialEq)]
+pub struct LinkEntry {
+    pub url: String,
+    pub text: String,
+}
+
+/// Extract `<a href>` targets as absolute http(s) URLs with their anchor text.
+///
+/// Fragments are dropped and links are deduplicated by URL, keeping the first non-empty text.
+pub fn extract_links(html: &str, base_url: &str) -> Vec<LinkEntry> {
+    let document = Html::parse_document(html);
+    let anchors = Selector::parse("a[href]").unwrap();
+    let base = Url::parse(base_url).ok();
+    let mut links: Vec<LinkEntry> = Vec::new();
+
+    for anchor in document.select(&anchors) {
+        let href = anchor.value().attr("href").unwrap_or_default().trim();
+        let resolved = match &base {
+            Some(base) => base.join(href),
+            None => Url::parse(href),
+        };
+        let Ok(mut url) = resolved else {
+            continue;
+        };
+        if !matches!(url.scheme(), "http" | "https") {
+            continue;
+        }
+        url.set_fragment(None);
+
+        let url = url.to_string();
+        let text = anchor
+            .text()
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        match links.iter_mut().find(|link| link.url == url) {
+            Some(existing) if existing.text.is_empty() => existing.text = text,
+            Some(_) => {}
+            None => links.push(LinkEntry { url, text }),
+        }
+    }
+
+    links
+}
+
+/// Resolve relative image sources against the page URL
+fn resolve_image_urls(images: &mut [ImageEntry], base_url: &str) {
+    let Ok(base) = Url::parse(base_url) else {
+        return;
+    };
+
+    for image in images.iter_mut() {
+        if let Ok(resolved) = base.join(&image.src) {
+            image.src = resolved.to_string();
+        }
+    }
+}
+
+/// A redirect followed by `fetch`: the URL requested and the status that redirected it
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RedirectHop {
+    pub url: String,
+    pub status: u16,
+}
+
+/// Render a redirect chain ending at `last`, e.g. `http://a (301) -> https://a`
+fn describe_redirects(redirects: &[RedirectHop], last: &str) -> String {
+    redirects
+        .iter()
+        .map(|hop| format!("{} ({})", hop.url, hop.status))
+        .chain(std::iter::once(last.to_string()))
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+/// Redirects followed by one `fetch`, and every URL it has requested
+struct RedirectChain {
+    hops: Vec<RedirectHop>,
+    visited: HashSet<String>,
+    max_redirects: usize,
+}
+
+impl RedirectChain {
+    fn new(url: &str, max_redirects: usize) -> Self {
+        Self {
+            hops: Vec::new(),
+            visited: HashSet::from([url.to_string()]),
+            max_redirects,
+        }
+    }
+
+    /// Record the redirect `status` from `current_url` to `target` and move `current_url` on.
+    ///
+    /// Fails when `target` was already requested, since a cycle would only end at
+    /// `max_redirects`, or when the redirect is one more than `max_redirects` allows.
+    fn follow(
+        &mut self,
+        current_url: &mut String,
+        status: u16,
+        target: String,
+    ) -> Result<(), ToolError> {
+        self.hops.push(RedirectHop {
+            url: std::mem::replace(current_url, target),
+            status,
+        });
+
+        if !self.visited.insert(current_url.clone()) {
+            return Err(ToolError::new(
+                ErrorKind::HttpStatus,
+                format!(
+                    "Redirect loop detected at: {} ({})",
+                    current_url,
+                    describe_redirects(&self.hops, current_url)
+                ),
+            )
+            .with_details(serde_json::json!({ "redirects": self.hops })));
+        }
+
+        if self.hops.len() > self.max_redirects {
+            return Err(ToolError::new(
+                ErrorKind::HttpStatus,
+                format!(
+                    "Too many redirects ({})",
+                    describe_redirects(&self.hops, current_url)
+                ),
+            )
+            .with_details(serde_json::json!({
+                "max_redirects": self.max_redirects,
+                "redirects": self.hops,
+            })));
+        }
+        Ok(())
+    }
+}
+
+/// Response to `fetch`, after following redirects
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct FetchResponse {
+    /// URL the content was finally served from
+    pub url: String,
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+    /// Raw body of binary content, which is never decoded: `body` is then empty
+    pub binary: Option<Vec<u8>>,
+    /// Redirects followed before `url`, in order
+    pub redirects: Vec<RedirectHop>,
+}
+
+/// Look up a header by name, ignoring case: neither servers nor the host normalize it
+pub fn find_header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+impl FetchResponse {
+    /// Look up a header by name, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.header("content-type")
+    }
+}
+
+/// `Accept-Language` sent with browse requests: `BROWSE_ACCEPT_LANGUAGE`, else the
+/// SearXNG search language when it names an actual language, else `en`
+fn accept_language() -> String {
+    let search_language = config::get("SEARXNG_DEFAULT_LANGUAGE")
+        .ok()
+        .flatten()
+        .filter(|l| !l.trim().is_empty() && !matches!(l.trim(), "all" | "auto"));
+    config::get("BROWSE_ACCEPT_LANGUAGE")
+        .ok()
+        .flatten()
+        .filter(|l| !l.trim().is_empty())
+        .or(search_language)
+        .unwrap_or_else(|| "en".to_string())
+}
+
+/// Credentials attached to browse requests as an `Authorization` header
+#[derive(Debug, Clone, PartialEq)]
+enum BrowseAuth {
+    /// `BROWSE_AUTH_BEARER`: a bearer token
+    Bearer(String),
+    /// `BROWSE_AUTH_BASIC`: `user:pass` for HTTP basic auth
+    Basic(String),
+}
+
+impl BrowseAuth {
+    /// Read credentials from config, preferring a bearer token when both are set
+    fn from_config() -> Option<Self> {
+        let value = |key: &str| {
+            config::get(key)
+                .ok()
+                .flatten()
+                .filter(|v| !v.trim().is_empty())
+        };
+        value("BROWSE_AUTH_BEARER")
+            .map(BrowseAuth::Bearer)
+            .or_else(|| value("BROWSE_AUTH_BASIC").map(BrowseAuth::Basic))
+    }
+
+    fn header_value(&self) -> String {
+        match self {
+            BrowseAuth::Bearer(token) => format!("Bearer {}", token.trim()),
+            BrowseAuth::Basic(credentials) => format!("Basic {}", STANDARD.encode(credentials)),
+        }
+    }
+}
+
+/// Hosts that may receive browse credentials, from the comma-separated `BROWSE_AUTH_HOSTS`
+fn auth_hosts() -> Vec<String> {
+    config::get("BROWSE_AUTH_HOSTS")
+        .ok()
+        .flatten()
+        .map(|hosts| {
+            hosts
+                .split(',')
+                .map(|h| h.trim().to_lowercase())
+                .filter(|h| !h.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `Authorization` value for a request to `url` made while browsing `origin`.
+///
+/// Credentials only go to hosts listed in `allowed_hosts`, never to every site browsed, so
+/// none are sent while the list is empty. They also stay on the host the browse started on:
+/// a redirect to another host drops them.
+fn authorization_for(
+    url: &str,
+    origin: &str,
+    auth: Option<&BrowseAuth>,
+    allowed_hosts: &[String],
+) -> Option<String> {
+    let auth = auth?;
+    let host = |u: &str| {
+        let parsed = Url::parse(u).ok()?;
+        Some((
+            parsed.host_str()?.to_lowercase(),
+            parsed.port_or_known_default(),
+        ))
+    };
+    let (target_host, target_port) = host(url)?;
+    if host(origin)? != (target_host.clone(), target_port) {
+        return None;
+    }
+    if !allowed_hosts.contains(&target_host) {
+        return None;
+    }
+    Some(auth.header_value())
+}
+
+/// Make an agent-supplied URL fetchable: trim it and assume `https://` when it has no
+/// scheme (`example.com/page`), then make sure it parses
+pub fn normalize_url(url: &str) -> Result<String, String> {
+    let trimmed = url.trim();
+    if trimmed.is_empty() {
+        return Err("URL is empty".to_string());
+    }
+    let candidate = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed.trim_start_matches('/'))
+    };
+    Url::parse(&candidate)
+        .map(|parsed| parsed.to_string())
+        .map_err(|e| format!("Invalid URL '{}': {}", trimmed, e))
+}
+
+/// Check that a URL can be browsed: it must parse, use `http` or `https`, and not point at
+/// the local machine, so the plugin cannot be used to reach services on the host (SSRF)
+pub fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!(
+            "Unsupported URL scheme '{}': only http and https can be browsed",
+            parsed.scheme()
+        ));
+    }
+
+    let is_local = match parsed.host() {
+        Some(Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        Some(Host::Ipv4(ip)) => ip == Ipv4Addr::LOCALHOST,
+        Some(Host::Ipv6(ip)) => ip == Ipv6Addr::LOCALHOST,
+        None => return Err(format!("URL has no host: {}", url)),
+    };
+    if is_local {
+        return Err(format!(
+            "Refusing to browse local address '{}'",
+            parsed.host_str().unwrap_or_default()
+        ));
+    }
+    Ok(())
+}
+
+/// Host name suffixes that only resolve inside a private network
+const PRIVATE_HOST_SUFFIXES: &[&str] = &[".local", ".localhost", ".internal", ".lan", ".home.arpa"];
+
+/// Whether an address is loopback, unspecified, RFC 1918 private or link-local, including
+/// IPv4 addresses mapped into IPv6 and the IPv6 unique-local and link-local ranges
+fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback() || ip.is_unspecified() || ip.is_private() || ip.is_link_local()
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_private_ip(IpAddr::V4(mapped)),
+            None => {
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+            }
+        },
+    }
+}
+
+/// Refuse URLs whose host is a private or link-local IP, or a name that only resolves on a
+/// private network (a single label like `intranet`, or a suffix such as `.local`).
+///
+/// Only the URL is checked: a public name whose DNS points at a private address is not
+/// caught, because the plugin cannot resolve names itself inside WASM.
+fn check_private_host(url: &str) -> Result<(), String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    let is_private = match parsed.host() {
+        Some(Host::Ipv4(ip)) => is_private_ip(IpAddr::V4(ip)),
+        Some(Host::Ipv6(ip)) => is_private_ip(IpAddr::V6(ip)),
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.').to_lowercase();
+            !domain.contains('.')
+                || PRIVATE_HOST_SUFFIXES
+                    .iter()
+                    .any(|suffix| domain.ends_with(suffix))
+        }
+        None => false,
+    };
+    if is_private {
+        return Err(format!(
+            "Refusing to browse private address '{}' (set BROWSE_ALLOW_PRIVATE_HOSTS to allow it)",
+            parsed.host_str().unwrap_or_default()
+        ));
+    }
+    Ok(())
+}
+
+/// Check a URL before requesting it: `validate_url`, then private hosts unless
+/// `BROWSE_ALLOW_PRIVATE_HOSTS` is enabled
+pub fn check_browse_target(url: &str) -> Result<(), String> {
+    validate_url(url)?;
+    if !config_flag("BROWSE_ALLOW_PRIVATE_HOSTS") {
+        check_private_host(url)?;
+    }
+    Ok(())
+}
+
+/// Read a comma-separated list of domains from config, lowercased
+fn domain_list(key: &str) -> Vec<String> {
+    config::get(key)
         .ok()
         .flatten()
-        .unwrap_or_else(|| "false".to_string());
-    let follow_redirects = follow_redirects_str == "true";
+        .map(|list| {
+            list.split(',')
+                .map(|d| d.trim().trim_start_matches('.').to_lowercase())
+                .filter(|d| !d.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
 
-    let max_redirects_str = config::get("BROWSE_MAX_REDIRECTS")
+/// Whether `host` is `domain` or one of its subdomains
+fn host_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.ends_with('.'))
+}
+
+/// Check a URL's host against `BROWSE_DOMAIN_BLOCKLIST` and `BROWSE_DOMAIN_ALLOWLIST`.
+/// Entries match the domain and its subdomains; the blocklist wins over the allowlist.
+fn check_domain(url: &str, allowlist: &[String], blocklist: &[String]) -> Result<(), ToolError> {
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_lowercase))
+        .ok_or_else(|| {
+            ToolError::new(
+                ErrorKind::InvalidArgument,
+                format!("URL has no host: {}", url),
+            )
+        })?;
+
+    if blocklist.iter().any(|domain| host_matches(&host, domain)) {
+        return Err(ToolError::new(
+            ErrorKind::InvalidArgument,
+            format!("Domain blocked: {}", host),
+        ));
+    }
+    if !allowlist.is_empty() && !allowlist.iter().any(|domain| host_matches(&host, domain)) {
+        return Err(ToolError::new(
+            ErrorKind::InvalidArgument,
+            "Domain not in allowlist",
+        ));
+    }
+    Ok(())
+}
+
+/// Resolve a redirect's `Location` header against the URL that returned it
+fn redirect_target(current_url: &str, location: &str) -> Result<String> {
+    if location.starts_with("http") {
+        return Ok(location.to_string());
+    }
+
+    let base = Url::parse(current_url).map_err(|e| {
+        ToolError::new(
+            ErrorKind::InvalidArgument,
+            format!("Failed to parse current URL: {}", e),
+        )
+    })?;
+    let target = base.join(location).map_err(|e| {
+        ToolError::new(
+            ErrorKind::Parse,
+            format!("Failed to resolve relative URL: {}", e),
+        )
+    })?;
+    Ok(target.to_string())
+}
+
+/// Error for a redirect that was not followed, naming the target so it can be browsed directly
+fn redirect_not_followed_error(status: u16, target: &str) -> ToolError {
+    ToolError::new(
+        ErrorKind::HttpStatus,
+        format!(
+            "HTTP {} redirect to {} was not followed: browse that URL directly or enable follow_redirects",
+            status, target
+        ),
+    )
+    .with_details(serde_json::json!({ "status": status, "location": target }))
+}
+
+/// Error for a non-success response, with actionable messages for 401 and 403
+fn http_status_error(status: u16, headers: &HashMap<String, String>, body: &str) -> ToolError {
+    let message = match status {
+        401 => match find_header(headers, "www-authenticate") {
+            Some(challenge) => format!(
+                "Authentication required: the resource needs credentials (WWW-Authenticate: {})",
+                challenge
+            ),
+            None => "Authentication required: the resource needs credentials".to_string(),
+        },
+        403 => "Access forbidden: the server rejected the request".to_string(),
+        _ => format!("HTTP Error: {} - {}", status, body),
+    };
+    ToolError::new(ErrorKind::HttpStatus, message)
+        .with_details(serde_json::json!({ "status": status }))
+}
+
+/// Error for a 429 response, with the `Retry-After` delay in seconds when the server gave one
+fn rate_limited_error(url: &str, headers: &HashMap<String, String>) -> ToolError {
+    let host = Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string());
+    let retry_after =
+        find_header(headers, "retry-after").and_then(|value| parse_retry_after(value, unix_now()));
+
+    match retry_after {
+        Some(seconds) => ToolError::new(
+            ErrorKind::RateLimited,
+            format!("{} rate limited this client, retry in {}s", host, seconds),
+        )
+        .with_details(serde_json::json!({ "retry_after_seconds": seconds })),
+        None => ToolError::new(
+            ErrorKind::RateLimited,
+            format!("{} rate limited this client, retry later", host),
+        ),
+    }
+}
+
+/// Redirect statuses that must name their target in a `Location` header
+const REDIRECT_STATUSES: &[u16] = &[301, 302, 303, 307, 308];
+
+/// Default for `BROWSE_MAX_REDIRECTS`
+const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+/// Default for `BROWSE_MAX_RESPONSE_BYTES`
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 10_000_000;
+
+/// Largest response body `fetch` accepts, so a huge download cannot exhaust plugin memory
+fn max_response_bytes() -> usize {
+    config::get("BROWSE_MAX_RESPONSE_BYTES")
         .ok()
         .flatten()
-        .unwrap_or_else(|| "10".to_string());
-    let max_redirects: usize = max_redirects_str.parse().unwrap_or(10);
+        .and_then(|s| s.trim().replace('_', "").parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_MAX_RESPONSE_BYTES)
+}
+
+/// Refuse a response body bigger than `limit` before it is decoded and converted
+fn check_response_size(size: usize, limit: usize) -> Result<(), ToolError> {
+    if size > limit {
+        return Err(ToolError::new(
+            ErrorKind::Internal,
+            format!(
+                "Response too large: {} bytes exceeds limit of {} bytes",
+                size, limit
+            ),
+        )
+        .with_details(serde_json::json!({ "size": size, "limit": limit })));
+    }
+    Ok(())
+}
+
+/// User agent sent when browsing, from `BROWSE_USER_AGENT`
+fn browse_user_agent() -> String {
+    config::get("BROWSE_USER_AGENT")
+        .ok()
+        .flatten()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| format!("hyper-mcp-search-browse/{}", env!("CARGO_PKG_VERSION")))
+}
 
+/// Fetch a URL, following redirects if configured, and decode the body as UTF-8
+pub fn fetch(url: &str) -> Result<FetchResponse> {
+    fetch_with(url, &FetchOptions::default())
+}
+
+/// `fetch` with per-call overrides
+pub fn fetch_with(url: &str, options: &FetchOptions) -> Result<FetchResponse> {
+    let url = &normalize_url(url).map_err(|e| ToolError::new(ErrorKind::InvalidArgument, e))?;
+    let follow_redirects = options.follow_redirects.unwrap_or_else(|| {
+        config::get("BROWSE_FOLLOW_REDIRECTS")
+            .ok()
+            .flatten()
+            .map(|s| s != "false")
+            .unwrap_or(true)
+    });
+
+    let max_redirects = options.max_redirects.unwrap_or_else(|| {
+        config::get("BROWSE_MAX_REDIRECTS")
+            .ok()
+            .flatten()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_MAX_REDIRECTS)
+    });
+
+    let max_response_bytes = max_response_bytes();
+    let user_agent = options.user_agent.clone().unwrap_or_else(browse_user_agent);
+    let accept_language = accept_language();
+    let auth = BrowseAuth::from_config();
+    let allowed_hosts = auth_hosts();
+    if auth.is_some() && allowed_hosts.is_empty() {
+        warn!(
+            "BROWSE_AUTH_BEARER/BROWSE_AUTH_BASIC is set without BROWSE_AUTH_HOSTS, not sending credentials"
+        );
+    }
+    let allowlist = domain_list("BROWSE_DOMAIN_ALLOWLIST");
+    let blocklist = domain_list("BROWSE_DOMAIN_BLOCKLIST");
     let mut current_url = url.to_string();
+    let mut redirects = RedirectChain::new(url, max_redirects);
 
-    for _ in 0..max_redirects {
+    // The first request plus up to max_redirects redirects, which `redirects` enforces
+    loop {
+        // Checked on every hop so a redirect cannot lead to a local or blocked address
+        check_browse_target(&current_url)
+            .map_err(|e| ToolError::new(ErrorKind::InvalidArgument, e))?;
+        check_domain(&current_url, &allowlist, &blocklist)?;
         info!("Browsing: {}", current_url);
-        let request = HttpRequest::new(&current_url).with_method("GET");
+        let mut request = HttpRequest::new(&current_url)
+            .with_method("GET")
+            .with_header("User-Agent", &user_agent)
+            .with_header("Accept-Language", &accept_language);
+        if let Some(authorization) =
+            authorization_for(&current_url, url, auth.as_ref(), &allowed_hosts)
+        {
+            request = request.with_header("Authorization", authorization);
+        }
+
+        let response = http::request::<Vec<u8>>(&request, None).map_err(|e| {
+            ToolError::new(ErrorKind::Connection, format!("HTTP request failed: {}", e))
+        })?;
+
+        let status = response.status_code();
+
+        if (300..400).contains(&status) {
+            match find_header(response.headers(), "location") {
+                Some(location) if follow_redirects => {
+                    let target = redirect_target(&current_url, location)?;
+                    redirects.follow(&mut current_url, status, target)?;
+                    continue;
+                }
+                Some(location) => {
+                    let target = redirect_target(&current_url, location)?;
+                    return Err(redirect_not_followed_error(status, &target).into());
+                }
+                // The body of such a response is only a "Moved" stub, not worth rendering
+                None if REDIRECT_STATUSES.contains(&status) => {
+                    return Err(ToolError::new(
+                        ErrorKind::HttpStatus,
+                        format!("HTTP {} redirect without a Location header", status),
+                    )
+                    .with_details(serde_json::json!({ "status": status }))
+                    .into());
+                }
+                None => {}
+            }
+        }
+
+        if status == 429 {
+            let error = rate_limited_error(&current_url, response.headers());
+            warn!("{}", error.message);
+            return Err(error.into());
+        }
+
+        // Not a redirect or not following redirects, process the response
+        let is_success =
+            (200..300).contains(&status) || (status == 0 && !response.body().is_empty());
+
+        if !is_success {
+            let body = String::from_utf8(response.body().to_vec())
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(http_status_error(status, response.headers(), &body).into());
+        }
+
+        check_response_size(response.body().len(), max_response_bytes)?;
+
+        if is_binary(
+            find_header(response.headers(), "content-type"),
+            &response.body(),
+        ) {
+            info!("Binary content from {}, not decoding it", current_url);
+            return Ok(FetchResponse {
+                url: current_url,
+                status,
+                headers: response.headers().clone(),
+                body: String::new(),
+                binary: Some(response.body()),
+                redirects: redirects.hops,
+            });
+        }
+
+        let (body, lossy) = decode_body(
+            &response.body(),
+            find_header(response.headers(), "content-type"),
+        );
+        if lossy {
+            warn!(
+                "Could not determine the encoding of {}, invalid UTF-8 was replaced",
+                current_url
+            );
+        }
+
+        return Ok(FetchResponse {
+            url: current_url,
+            status,
+            headers: response.headers().clone(),
+            body,
+            binary: None,
+            redirects: redirects.hops,
+        });
+    }
+}
+
+/// Finds a `<meta charset>` or `<meta http-equiv="Content-Type" content="...; charset=...">`
+static META_CHARSET_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<meta\b[^>]*?charset\s*=\s*["']?\s*([a-z0-9_:.\-]+)"#).unwrap()
+});
+
+/// How many leading bytes of a page are searched for a `<meta>` charset declaration
+const CHARSET_SNIFF_BYTES: usize = 1024;
+
+/// Charset declared by a `Content-Type` header or, failing that, by a `<meta>` tag near the
+/// start of the body
+fn declared_encoding(body: &[u8], content_type: Option<&str>) -> Option<&'static Encoding> {
+    let from_header = content_type.and_then(|content_type| {
+        content_type.split(';').skip(1).find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("charset")
+                .then(|| value.trim().trim_matches(['"', '\'']))
+        })
+    });
+    if let Some(encoding) = from_header.and_then(|label| Encoding::for_label(label.as_bytes())) {
+        return Some(encoding);
+    }
+
+    let head = String::from_utf8_lossy(&body[..body.len().min(CHARSET_SNIFF_BYTES)]);
+    META_CHARSET_RE
+        .captures(&head)
+        .and_then(|caps| Encoding::for_label(caps[1].as_bytes()))
+}
+
+/// Decode a response body to UTF-8 using its declared charset (a byte order mark wins),
+/// else as UTF-8. Returns the text and whether undecodable bytes had to be replaced.
+fn decode_body(body: &[u8], content_type: Option<&str>) -> (String, bool) {
+    let encoding = declared_encoding(body, content_type).unwrap_or(UTF_8);
+    let (text, _, had_errors) = encoding.decode(body);
+    (text.into_owned(), had_errors)
+}
+
+/// Whether a page looks paywalled: a known paywall container, or most of the
+/// body text hidden via inline `display:none`
+fn detect_paywall(html: &str) -> bool {
+    let document = Html::parse_document(html);
+
+    let marked = Selector::parse("[class], [id]").unwrap();
+    let has_marker = document.select(&marked).any(|element| {
+        let names = format!(
+            "{} {}",
+            element.value().attr("class").unwrap_or_default(),
+            element.value().attr("id").unwrap_or_default()
+        )
+        .to_lowercase();
+        PAYWALL_MARKERS.iter().any(|marker| names.contains(marker))
+    });
+    if has_marker {
+        return true;
+    }
+
+    let text_length =
+        |element: ElementRef| -> usize { element.text().map(|t| t.trim().chars().count()).sum() };
+
+    let body = Selector::parse("body").unwrap();
+    let total = document.select(&body).next().map(text_length).unwrap_or(0);
+    if total == 0 {
+        return false;
+    }
+
+    let is_hidden = |element: &ElementRef| {
+        element
+            .value()
+            .attr("style")
+            .map(|style| {
+                let style: String = style.chars().filter(|c| !c.is_whitespace()).collect();
+                style.to_lowercase().contains("display:none")
+            })
+            .unwrap_or(false)
+    };
+
+    let styled = Selector::parse("[style]").unwrap();
+    let hidden: usize = document
+        .select(&styled)
+        .filter(|element| is_hidden(element))
+        // Skip hidden elements nested inside another hidden element so text isn't counted twice
+        .filter(|element| {
+            !element
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .any(|a| is_hidden(&a))
+        })
+        .map(text_length)
+        .sum();
+
+    hidden as f64 / total as f64 > HIDDEN_TEXT_RATIO
+}
+
+/// Read the Open Graph `og:*` meta tags of a page; the first occurrence of each wins
+pub fn extract_open_graph(html: &str) -> OpenGraph {
+    let document = Html::parse_document(html);
+    let meta = Selector::parse("meta[property][content]").unwrap();
+    let mut open_graph = OpenGraph::default();
+
+    for element in document.select(&meta) {
+        let property = element.value().attr("property").unwrap_or_default();
+        let content = element.value().attr("content").unwrap_or_default().trim();
+        if content.is_empty() {
+            continue;
+        }
+
+        let field = match property.to_lowercase().as_str() {
+            "og:title" => &mut open_graph.title,
+            "og:description" => &mut open_graph.description,
+            "og:image" => &mut open_graph.image,
+            "og:type" => &mut open_graph.og_type,
+            "og:url" => &mut open_graph.url,
+            _ => continue,
+        };
+        if field.is_none() {
+            *field = Some(content.to_string());
+        }
+    }
+
+    open_graph
+}
+
+/// Parse the schema.org `<script type="application/ld+json">` blocks of a page.
+/// Top-level arrays are flattened and blocks that are not valid JSON are skipped.
+pub fn extract_json_ld(html: &str) -> Vec<serde_json::Value> {
+    let document = Html::parse_document(html);
+    let scripts = Selector::parse("script[type]").unwrap();
+
+    let mut items = Vec::new();
+    for script in document.select(&scripts) {
+        let script_type = script.value().attr("type").unwrap_or_default();
+        if !script_type
+            .trim()
+            .eq_ignore_ascii_case("application/ld+json")
+        {
+            continue;
+        }
+
+        let text: String = script.text().collect();
+        match serde_json::from_str::<serde_json::Value>(text.trim()) {
+            Ok(serde_json::Value::Array(values)) => items.extend(values),
+            Ok(value) => items.push(value),
+            Err(_) => continue,
+        }
+    }
+
+    items
+}
+
+/// Keep only the outer HTML of elements matching the CSS selectors, all matches of the first
+/// selector, then of the next. `None` when nothing matched.
+fn select_fragment(html: &str, selectors: &[String]) -> Result<Option<String>> {
+    let parsed_selectors = selectors
+        .iter()
+        .map(|selector| {
+            Selector::parse(selector).map_err(|e| {
+                ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("Invalid selector '{}': {}", selector, e),
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let document = Html::parse_document(html);
+
+    let fragments: Vec<String> = parsed_selectors
+        .iter()
+        .flat_map(|selector| document.select(selector).map(|element| element.html()))
+        .collect();
+
+    Ok((!fragments.is_empty()).then(|| fragments.join("\n")))
+}
+
+/// Note returned instead of content when no element matched, naming the page so it is clear
+/// the fetch itself worked
+fn no_match_note(selectors: &[String], title: Option<&str>, url: &str) -> String {
+    let selectors = selectors
+        .iter()
+        .map(|selector| format!("'{}'", selector))
+        .collect::<Vec<_>>()
+        .join(", ");
+    match title {
+        Some(title) => format!(
+            "0 elements matched {} on \"{}\" ({})",
+            selectors, title, url
+        ),
+        None => format!("0 elements matched {} on {}", selectors, url),
+    }
+}
+
+pub fn browse(url: &str, options: &BrowseOptions) -> Result<BrowseOutput> {
+    let mut page = fetch_with(url, &options.fetch)?;
+
+    // PDFs, images and archives are described rather than converted
+    if let Some(bytes) = page.binary.take() {
+        let content_type = page
+            .content_type()
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        return Ok(BrowseOutput {
+            markdown: binary_summary(&page.url, &content_type, bytes.len()),
+            mime_type: "application/json",
+            metadata: PageMetadata::from_markdown("").with_response(&page, bytes.len()),
+            open_graph: OpenGraph::default(),
+            json_ld: Vec::new(),
+            binary: Some(bytes),
+        });
+    }
+
+    let size_bytes = page.body.len();
+
+    // JSON APIs have no HTML to convert; show the document itself
+    if page.content_type().is_some_and(is_json_content_type) {
+        let (text, mime_type) = match pretty_json(&page.body) {
+            Some(pretty) => (pretty, "application/json"),
+            None => (std::mem::take(&mut page.body), "text/plain"),
+        };
+        return Ok(BrowseOutput {
+            metadata: PageMetadata::from_markdown(&text).with_response(&page, size_bytes),
+            markdown: text,
+            mime_type,
+            open_graph: OpenGraph::default(),
+            json_ld: Vec::new(),
+            binary: None,
+        });
+    }
+
+    // Feeds become a list of their entries; anything that fails to parse is treated as HTML
+    if (page.content_type().is_some_and(declares_feed) || is_feed(&page.body))
+        && let Ok(entries) = parse_feed(&page.body)
+        && !entries.is_empty()
+    {
+        let markdown = feed_markdown(&entries);
+        return Ok(BrowseOutput {
+            metadata: PageMetadata::from_markdown(&markdown).with_response(&page, size_bytes),
+            markdown,
+            mime_type: "text/markdown",
+            open_graph: OpenGraph::default(),
+            json_ld: Vec::new(),
+            binary: None,
+        });
+    }
+
+    // Plain text would only be mangled by the HTML pipeline
+    if !renders_as_html(page.content_type(), &page.body) {
+        let text = std::mem::take(&mut page.body);
+        return Ok(BrowseOutput {
+            metadata: PageMetadata::from_markdown(&text).with_response(&page, size_bytes),
+            markdown: text,
+            mime_type: "text/plain",
+            open_graph: OpenGraph::default(),
+            json_ld: Vec::new(),
+            binary: None,
+        });
+    }
+
+    let mut html = std::mem::take(&mut page.body);
+    let title = extract_title(&html);
+
+    let open_graph = extract_open_graph(&html);
+    let json_ld = extract_json_ld(&html);
+
+    if config_flag("BROWSE_DETECT_PAYWALL") && detect_paywall(&html) {
+        return Err(PaywallDetected.into());
+    }
+
+    // An explicit selector decides what is kept, landmarks included
+    if !options.selectors.is_empty() {
+        match select_fragment(&html, &options.selectors)? {
+            Some(fragment) => html = fragment,
+            None => {
+                let note = no_match_note(&options.selectors, title.as_deref(), &page.url);
+                return Ok(BrowseOutput {
+                    metadata: PageMetadata {
+                        title,
+                        ..PageMetadata::from_markdown("").with_response(&page, size_bytes)
+                    },
+                    markdown: note,
+                    mime_type: "text/plain",
+                    open_graph,
+                    json_ld,
+                    binary: None,
+                });
+            }
+        }
+    } else if config::get("BROWSE_STRIP_LANDMARKS")
+        .ok()
+        .flatten()
+        .map(|s| s != "false")
+        .unwrap_or(true)
+    {
+        html = strip_landmarks(&html);
+    }
+
+    let readability = options
+        .readability
+        .unwrap_or_else(|| config_flag("BROWSE_READABILITY"));
+    if readability {
+        html = extract_main_content(&html);
+    }
+
+    // Strip <style> and <script> tags from HTML before converting to markdown
+    let mut cleaned_html = strip_html_comments(&strip_styles_and_scripts(&html));
+    if config_flag("BROWSE_STRIP_IMAGES") {
+        cleaned_html = strip_images(&cleaned_html);
+    }
+    if config_flag("BROWSE_STRIP_LINKS") {
+        cleaned_html = strip_links(&cleaned_html);
+    }
+
+    let (markdown, mime_type, metadata) = match options.format {
+        PageFormat::Markdown => {
+            let mut markdown = collapse_blank_lines(&html_to_markdown(&cleaned_html));
+            let metadata = PageMetadata::from_markdown(&markdown);
+            if options.generate_toc {
+                let toc = generate_toc(&markdown);
+                if !toc.is_empty() {
+                    markdown = format!("{}\n{}", toc, markdown);
+                }
+            }
+            (markdown, "text/markdown", metadata)
+        }
+        // Words are counted in the text, not the markup
+        PageFormat::Html => {
+            let metadata = PageMetadata::from_markdown(&html_to_text(&cleaned_html));
+            (cleaned_html, "text/html", metadata)
+        }
+        PageFormat::Text => {
+            let text = html_to_text(&cleaned_html);
+            let metadata = PageMetadata::from_markdown(&text);
+            (text, "text/plain", metadata)
+        }
+    };
+    let metadata = PageMetadata {
+        title,
+        ..metadata.with_response(&page, size_bytes)
+    };
+
+    Ok(BrowseOutput {
+        metadata,
+        markdown,
+        mime_type,
+        open_graph,
+        json_ld,
+        binary: None,
+    })
+}
+
+/// Whether a `Content-Type` is JSON: `application/json` or a `+json` type such as
+/// `application/problem+json`
+fn is_json_content_type(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+}
+
+/// Pretty-print a JSON document, or `None` if it does not parse
+fn pretty_json(body: &str) -> Option<String> {
+    serde_json::from_str::<serde_json::Value>(body)
+        .and_then(|value| serde_json::to_string_pretty(&value))
+        .ok()
+}
+
+/// Content types that are binary whatever their body looks like
+const BINARY_CONTENT_TYPES: &[&str] = &[
+    "application/pdf",
+    "application/octet-stream",
+    "application/zip",
+    "application/gzip",
+    "application/x-gzip",
+    "application/x-tar",
+    "application/x-7z-compressed",
+    "application/vnd.rar",
+    "application/x-rar-compressed",
+    "application/msword",
+    "application/vnd.ms-excel",
+    "application/vnd.ms-powerpoint",
+    "application/wasm",
+];
+
+/// Content type families that are binary, except for the text formats among them such as SVG
+const BINARY_CONTENT_TYPE_PREFIXES: &[&str] = &[
+    "image/",
+    "audio/",
+    "video/",
+    "font/",
+    "application/vnd.openxmlformats-officedocument.",
+];
+
+/// How many leading bytes of a body are searched for a null byte
+const BINARY_SNIFF_BYTES: usize = 1024;
+
+/// Default of `BROWSE_MAX_BINARY_BYTES`
+const DEFAULT_MAX_BINARY_BYTES: usize = 1_000_000;
+
+/// Largest binary body the browse tool returns as base64 when asked to
+pub fn max_binary_bytes() -> usize {
+    config::get("BROWSE_MAX_BINARY_BYTES")
+        .ok()
+        .flatten()
+        .and_then(|s| s.trim().replace('_', "").parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or(DEFAULT_MAX_BINARY_BYTES)
+}
+
+/// Lowercased media type of a `Content-Type` header, without its parameters
+pub fn media_type(content_type: &str) -> String {
+    content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+/// Whether a body is binary, going by its declared content type or a null byte near its start.
+/// UTF-16 text has null bytes too, so a body opening with a UTF-16 byte order mark is not binary.
+fn is_binary(content_type: Option<&str>, body: &[u8]) -> bool {
+    if let Some(content_type) = content_type {
+        let mime = media_type(content_type);
+        if BINARY_CONTENT_TYPES.contains(&mime.as_str())
+            || (BINARY_CONTENT_TYPE_PREFIXES
+                .iter()
+                .any(|prefix| mime.starts_with(prefix))
+                && !mime.ends_with("+xml"))
+        {
+            return true;
+        }
+    }
+    if body.starts_with(&[0xFF, 0xFE]) || body.starts_with(&[0xFE, 0xFF]) {
+        return false;
+    }
+    body[..body.len().min(BINARY_SNIFF_BYTES)].contains(&0)
+}
+
+/// JSON description of binary content that browse does not convert to text
+fn binary_summary(url: &str, content_type: &str, content_length: usize) -> String {
+    serde_json::to_string_pretty(&serde_json::json!({
+        "binary": true,
+        "url": url,
+        "content_type": content_type,
+        "content_length": content_length,
+        "note": "The content is binary and was not converted to text. Pass return_binary: true to receive small files as base64 data.",
+    }))
+    .unwrap_or_default()
+}
+
+/// Whether a body should go through the HTML to Markdown pipeline: HTML and XHTML do,
+/// plain text does not, and anything else is sniffed for a leading `<`
+fn renders_as_html(content_type: Option<&str>, body: &str) -> bool {
+    match content_type.map(media_type).as_deref() {
+        Some("text/html") | Some("application/xhtml+xml") => true,
+        Some("text/plain") => false,
+        _ => body.trim_start().starts_with('<'),
+    }
+}
+
+/// Fetch a page and return all of its images with absolute URLs
+pub fn browse_images(url: &str, options: &FetchOptions) -> Result<Vec<ImageEntry>> {
+    let page = fetch_with(url, options)?;
+
+    let mut images = extract_images(&page.body);
+    resolve_image_urls(&mut images, &page.url);
+
+    Ok(images)
+}
+
+/// Page listed in a sitemap's `<urlset>`
+#[derive(Debug, Serialize, PartialEq)]
+pub struct SitemapUrl {
+    pub loc: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lastmod: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
+}
+
+/// Parsed sitemap: page URLs, plus child sitemaps when it is a sitemap index
+#[derive(Debug, Default, PartialEq)]
+struct Sitemap {
+    urls: Vec<SitemapUrl>,
+    sitemaps: Vec<String>,
+}
+
+/// Point at `/sitemap.xml` when the URL is just a site root
+fn sitemap_location(url: &str) -> Result<String> {
+    let mut parsed = Url::parse(url).map_err(|e| {
+        ToolError::new(
+            ErrorKind::InvalidArgument,
+            format!("Invalid URL {}: {}", url, e),
+        )
+    })?;
+    if parsed.path() == "/" || parsed.path().is_empty() {
+        parsed.set_path("/sitemap.xml");
+    }
+    Ok(parsed.to_string())
+}
 
-        let response = http::request::<Vec<u8>>(&request, None)
-            .map_err(|e| anyhow!("HTTP request failed: {}", e))?;
+/// Parse a `<urlset>` or `<sitemapindex>` document
+fn parse_sitemap(xml: &str) -> Result<Sitemap> {
+    let mut reader = Reader::from_str(xml);
+    let mut sitemap = Sitemap::default();
+    let mut loc = None;
+    let mut lastmod = None;
+    let mut priority = None;
+    let mut text = String::new();
 
-        let status = response.status_code();
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            ToolError::new(
+                ErrorKind::Parse,
+                format!(
+                    "Failed to parse sitemap at position {}: {}",
+                    reader.error_position(),
+                    e
+                ),
+            )
+        })?;
 
-        if status >= 300 && status < 400 && follow_redirects {
-            if let Some(location) = response.headers().get("location") {
-                let location_str = location.clone();
-                let new_url = if location_str.starts_with("http") {
-                    location_str
-                } else {
-                    // relative URL, resolve against current_url
-                    let base = Url::parse(&current_url)
-                        .map_err(|e| anyhow!("Failed to parse current URL: {}", e))?;
-                    base.join(&location_str)
-                        .map_err(|e| anyhow!("Failed to resolve relative URL: {}", e))?
-                        .to_string()
-                };
-                current_url = new_url;
-                continue;
+        match event {
+            Event::Start(_) => text.clear(),
+            Event::Text(e) => text.push_str(&e.unescape().unwrap_or_default()),
+            Event::CData(e) => text.push_str(&String::from_utf8_lossy(&e)),
+            Event::End(e) => {
+                let value = text.trim().to_string();
+                text.clear();
+                match e.local_name().as_ref() {
+                    b"loc" if !value.is_empty() => loc = Some(value),
+                    b"lastmod" if !value.is_empty() => lastmod = Some(value),
+                    b"priority" => priority = value.parse().ok(),
+                    b"url" => {
+                        if let Some(loc) = loc.take() {
+                            sitemap.urls.push(SitemapUrl {
+                                loc,
+                                lastmod: lastmod.take(),
+                                priority: priority.take(),
+                            });
+                        }
+                        lastmod = None;
+                        priority = None;
+                    }
+                    b"sitemap" => {
+                        if let Some(loc) = loc.take() {
+                            sitemap.sitemaps.push(loc);
+                        }
+                        lastmod = None;
+                        priority = None;
+                    }
+                    _ => {}
+                }
             }
+            Event::Eof => break,
+            _ => {}
         }
+    }
 
-        // Not a redirect or not following redirects, process the response
-        let is_success =
-            (200..300).contains(&status) || (status == 0 && !response.body().is_empty());
-
-        if !is_success {
-            let body = String::from_utf8(response.body().to_vec())
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(anyhow!("HTTP Error: {} - {}", status, body));
-        }
-
-        let html = String::from_utf8(response.body().to_vec())
-            .map_err(|e| anyhow!("Failed to decode response body: {}", e))?;
+    Ok(sitemap)
+}
 
-        // Strip <style> and <script> tags from HTML before converting to markdown
-        let cleaned_html = strip_styles_and_scripts(&html);
+/// Fetch a site's sitemap and list its pages. Sitemap indexes are followed one level deep;
+/// child sitemaps that fail to load are skipped.
+pub fn fetch_sitemap(base_url: &str) -> Result<Vec<SitemapUrl>> {
+    let page = fetch(&sitemap_location(base_url)?)?;
+    let sitemap = parse_sitemap(&page.body)?;
+    let mut urls = sitemap.urls;
 
-        return Ok(html2md::parse_html(&cleaned_html));
+    for child in sitemap.sitemaps {
+        match fetch(&child).and_then(|page| parse_sitemap(&page.body)) {
+            Ok(nested) => urls.extend(nested.urls),
+            Err(e) => warn!("Skipping sitemap {}: {}", child, e),
+        }
     }
 
-    Err(anyhow!("Too many redirects"))
+    Ok(urls)
+}
+
+/// Fetch a page and return its outbound links
+pub fn browse_links(url: &str) -> Result<Vec<LinkEntry>> {
+    let page = fetch(url)?;
+    Ok(extract_links(&page.body, &page.url))
 }
 
 #[cfg(test)]
@@ -157,4 +1928,1138 @@ mod tests {
         assert!(markdown.contains("Title"));
         assert!(markdown.contains("Content"));
     }
+
+    #[test]
+    fn test_strip_html_comments() {
+        let html = r#"<p>Before</p>
+<!-- build: 2024-01-01
+     debug: true -->
+<!--[if IE]><p>Upgrade your browser</p><![endif]-->
+<p>After <!-- inline --> text</p>"#;
+
+        let cleaned = strip_html_comments(html);
+        assert!(!cleaned.contains("debug"));
+        assert!(!cleaned.contains("Upgrade your browser"));
+        assert!(!cleaned.contains("<!--"));
+        assert!(cleaned.contains("<p>Before</p>"));
+        assert!(cleaned.contains("<p>After  text</p>"));
+    }
+
+    #[test]
+    fn test_decode_body_windows_1252() {
+        // "Café – naïve" in Windows-1252
+        let body = b"<p>Caf\xe9 \x96 na\xefve</p>";
+
+        assert_eq!(
+            decode_body(body, Some("text/html; charset=windows-1252")),
+            ("<p>Café – naïve</p>".to_string(), false)
+        );
+        // ISO-8859-1 is decoded as Windows-1252, as browsers do
+        assert_eq!(
+            decode_body(body, Some("text/html; charset=\"ISO-8859-1\"")).0,
+            "<p>Café – naïve</p>"
+        );
+
+        let with_meta = [
+            b"<html><head><meta http-equiv=\"Content-Type\" content=\"text/html; charset=windows-1252\"></head>".as_slice(),
+            body,
+        ]
+        .concat();
+        assert!(
+            decode_body(&with_meta, Some("text/html"))
+                .0
+                .contains("Café – naïve")
+        );
+    }
+
+    #[test]
+    fn test_decode_body_shift_jis() {
+        // "日本語" in Shift-JIS
+        let body = [
+            b"<meta charset=\"Shift_JIS\"><p>".as_slice(),
+            &[0x93, 0xfa, 0x96, 0x7b, 0x8c, 0xea],
+            b"</p>",
+        ]
+        .concat();
+
+        assert_eq!(
+            decode_body(&body, None),
+            (
+                "<meta charset=\"Shift_JIS\"><p>日本語</p>".to_string(),
+                false
+            )
+        );
+    }
+
+    #[test]
+    fn test_decode_body_falls_back_to_lossy_utf8() {
+        assert_eq!(
+            decode_body("<p>héllo</p>".as_bytes(), None),
+            ("<p>héllo</p>".to_string(), false)
+        );
+        assert_eq!(
+            decode_body(b"<p>caf\xe9</p>", Some("text/html")),
+            ("<p>caf\u{fffd}</p>".to_string(), true)
+        );
+        // An unknown label is ignored rather than failing
+        assert_eq!(
+            decode_body(b"ok", Some("text/plain; charset=made-up")),
+            ("ok".to_string(), false)
+        );
+    }
+
+    /// Timing comparison, run with `cargo test --release -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    #[allow(clippy::regex_creation_in_loops)]
+    fn bench_strip_styles_and_scripts_cached_regexes() {
+        let section = r#"<section class="post"><h2>Heading</h2><p>Some <a href="/x">linked</a> text with <em>emphasis</em>.</p>
+<style>.post { margin: 0 auto; color: #333; }</style>
+<script type="text/javascript">window.dataLayer = window.dataLayer || []; dataLayer.push({ event: "view" });</script></section>
+"#;
+        let html = format!(
+            "<html><head><title>Fixture</title></head><body>{}</body></html>",
+            section.repeat(50)
+        );
+        const ITERATIONS: u32 = 1000;
+
+        let started = std::time::Instant::now();
+        for _ in 0..ITERATIONS {
+            let style_re = Regex::new(r"(?is)<style[^>]*>.*?</style>").unwrap();
+            let script_re = Regex::new(r"(?is)<script[^>]*>.*?</script>").unwrap();
+            let without_styles = style_re.replace_all(&html, "");
+            std::hint::black_box(script_re.replace_all(&without_styles, "").to_string());
+        }
+        let per_call = started.elapsed();
+
+        let started = std::time::Instant::now();
+        for _ in 0..ITERATIONS {
+            std::hint::black_box(strip_styles_and_scripts(&html));
+        }
+        let cached = started.elapsed();
+
+        println!(
+            "{} iterations: compiled per call {:?}, cached {:?}",
+            ITERATIONS, per_call, cached
+        );
+    }
+
+    #[test]
+    fn test_extract_images_reads_attributes() {
+        let html = r#"
+        <html>
+        <body>
+        <img src="/logo.png" alt="Logo" width="120" height="40">
+        <IMG SRC='photo.jpg' ALT='A photo'/>
+        <img alt="no source">
+        <img src=https://cdn.example.com/banner.gif width=600>
+        </body>
+        </html>
+        "#;
+
+        let images = extract_images(html);
+        assert_eq!(images.len(), 3);
+        assert_eq!(
+            images[0],
+            ImageEntry {
+                src: "/logo.png".into(),
+                alt: Some("Logo".into()),
+                width: Some("120".into()),
+                height: Some("40".into()),
+            }
+        );
+        assert_eq!(images[1].src, "photo.jpg");
+        assert_eq!(images[1].alt.as_deref(), Some("A photo"));
+        assert_eq!(images[1].width, None);
+        assert_eq!(images[2].src, "https://cdn.example.com/banner.gif");
+        assert_eq!(images[2].width.as_deref(), Some("600"));
+    }
+
+    #[test]
+    fn test_resolve_image_urls_makes_sources_absolute() {
+        let mut images = extract_images(
+            r#"<img src="/logo.png"><img src="img/a.png"><img src="https://other.org/b.png">"#,
+        );
+        resolve_image_urls(&mut images, "https://example.com/blog/post.html");
+
+        assert_eq!(images[0].src, "https://example.com/logo.png");
+        assert_eq!(images[1].src, "https://example.com/blog/img/a.png");
+        assert_eq!(images[2].src, "https://other.org/b.png");
+    }
+
+    #[test]
+    fn test_select_fragment_keeps_only_matching_elements() {
+        let html = r#"
+        <html>
+        <body>
+        <nav>Home | About</nav>
+        <article class="main-content"><h1>Headline</h1><p>Story body</p></article>
+        <footer>Copyright</footer>
+        </body>
+        </html>
+        "#;
+
+        let fragment = select_fragment(html, &["article.main-content".into()])
+            .unwrap()
+            .unwrap();
+        assert!(fragment.starts_with("<article class=\"main-content\">"));
+        let markdown = html2md::parse_html(&fragment);
+        assert!(markdown.contains("Headline"));
+        assert!(markdown.contains("Story body"));
+        assert!(!markdown.contains("Home | About"));
+        assert!(!markdown.contains("Copyright"));
+
+        // Selectors are applied in the order given, not document order
+        let ordered = select_fragment(html, &["footer".into(), "h1".into()])
+            .unwrap()
+            .unwrap();
+        assert_eq!(ordered, "<footer>Copyright</footer>\n<h1>Headline</h1>");
+    }
+
+    #[test]
+    fn test_select_fragment_errors() {
+        let html = "<html><body><p>Text</p></body></html>";
+
+        let invalid = select_fragment(html, &["p".into(), "p[".into()]).unwrap_err();
+        assert!(invalid.to_string().contains("Invalid selector 'p['"));
+
+        assert_eq!(select_fragment(html, &["article".into()]).unwrap(), None);
+        assert_eq!(
+            no_match_note(
+                &["article".into(), "#main".into()],
+                Some("Home"),
+                "https://example.com/"
+            ),
+            "0 elements matched 'article', '#main' on \"Home\" (https://example.com/)"
+        );
+        assert_eq!(
+            no_match_note(&["article".into()], None, "https://example.com/"),
+            "0 elements matched 'article' on https://example.com/"
+        );
+    }
+
+    #[test]
+    fn test_detect_paywall_markers() {
+        let by_class = r#"<html><body><p>Teaser</p><div class="article-paywall">Subscribe</div></body></html>"#;
+        let by_id = r#"<html><body><p>Teaser</p><div id="premium-content"></div></body></html>"#;
+        assert!(detect_paywall(by_class));
+        assert!(detect_paywall(by_id));
+    }
+
+    #[test]
+    fn test_detect_paywall_hidden_text() {
+        let mostly_hidden = r#"
+        <html><body>
+        <p>Short teaser.</p>
+        <div style="display: none">The full article text that is only revealed to paying subscribers goes here.</div>
+        </body></html>
+        "#;
+        assert!(detect_paywall(mostly_hidden));
+
+        let mostly_visible = r#"
+        <html><body>
+        <p>The full article text is visible to everyone who opens this page in a browser.</p>
+        <div style="display:none">Menu</div>
+        </body></html>
+        "#;
+        assert!(!detect_paywall(mostly_visible));
+    }
+
+    #[test]
+    fn test_extract_open_graph() {
+        let html = r#"
+        <html>
+        <head>
+        <meta property="og:title" content="The Rock" />
+        <meta property="og:type" content="video.movie" />
+        <meta property="og:url" content="https://www.imdb.com/title/tt0117500/" />
+        <meta property="og:image" content="https://ia.media-imdb.com/images/rock.jpg" />
+        <meta property="og:description" content="Sean Connery found fame and fortune." />
+        <meta property="og:title" content="Duplicate title" />
+        <meta name="description" content="Plain description" />
+        </head>
+        <body><p>Body</p></body>
+        </html>
+        "#;
+
+        let open_graph = extract_open_graph(html);
+        assert_eq!(
+            open_graph,
+            OpenGraph {
+                title: Some("The Rock".into()),
+                description: Some("Sean Connery found fame and fortune.".into()),
+                image: Some("https://ia.media-imdb.com/images/rock.jpg".into()),
+                og_type: Some("video.movie".into()),
+                url: Some("https://www.imdb.com/title/tt0117500/".into()),
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&open_graph).unwrap()["type"],
+            "video.movie"
+        );
+    }
+
+    #[test]
+    fn test_extract_open_graph_missing() {
+        let open_graph = extract_open_graph("<html><head><title>x</title></head></html>");
+        assert!(open_graph.is_empty());
+    }
+
+    #[test]
+    fn test_extract_json_ld() {
+        let html = r#"
+        <html>
+        <head>
+        <script type="application/ld+json">
+        {"@context": "https://schema.org", "@type": "Article", "headline": "Launch day"}
+        </script>
+        <script type="Application/LD+JSON">[{"@type": "Person", "name": "Ada"}, {"@type": "Organization", "name": "ACME"}]</script>
+        <script type="application/ld+json">{ not json }</script>
+        <script>var data = {"@type": "Ignored"};</script>
+        </head>
+        <body><p>Body</p></body>
+        </html>
+        "#;
+
+        let items = extract_json_ld(html);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0]["headline"], "Launch day");
+        assert_eq!(items[1]["name"], "Ada");
+        assert_eq!(items[2]["@type"], "Organization");
+
+        let markdown = html2md::parse_html(&strip_styles_and_scripts(html));
+        assert!(!markdown.contains("Launch day"));
+    }
+
+    #[test]
+    fn test_sitemap_location() {
+        assert_eq!(
+            sitemap_location("https://example.com").unwrap(),
+            "https://example.com/sitemap.xml"
+        );
+        assert_eq!(
+            sitemap_location("https://example.com/").unwrap(),
+            "https://example.com/sitemap.xml"
+        );
+        assert_eq!(
+            sitemap_location("https://example.com/sitemap_index.xml").unwrap(),
+            "https://example.com/sitemap_index.xml"
+        );
+        assert!(sitemap_location("not a url").is_err());
+    }
+
+    #[test]
+    fn test_parse_sitemap() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+          <url>
+            <loc>https://example.com/</loc>
+            <lastmod>2024-01-01</lastmod>
+            <priority>1.0</priority>
+          </url>
+          <url>
+            <loc>https://example.com/about?lang=en&amp;v=2</loc>
+          </url>
+          <url><lastmod>2024-01-02</lastmod></url>
+        </urlset>"#;
+
+        let sitemap = parse_sitemap(xml).unwrap();
+        assert!(sitemap.sitemaps.is_empty());
+        assert_eq!(
+            sitemap.urls,
+            vec![
+                SitemapUrl {
+                    loc: "https://example.com/".into(),
+                    lastmod: Some("2024-01-01".into()),
+                    priority: Some(1.0),
+                },
+                SitemapUrl {
+                    loc: "https://example.com/about?lang=en&v=2".into(),
+                    lastmod: None,
+                    priority: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_sitemap_index() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+          <sitemap>
+            <loc>https://example.com/sitemap-posts.xml</loc>
+            <lastmod>2024-01-01</lastmod>
+          </sitemap>
+          <sitemap><loc>https://example.com/sitemap-pages.xml</loc></sitemap>
+        </sitemapindex>"#;
+
+        let sitemap = parse_sitemap(xml).unwrap();
+        assert!(sitemap.urls.is_empty());
+        assert_eq!(
+            sitemap.sitemaps,
+            vec![
+                "https://example.com/sitemap-posts.xml".to_string(),
+                "https://example.com/sitemap-pages.xml".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_links() {
+        let html = r##"
+        <html><body>
+        <nav><a href="/">Home</a> <a href="/docs/">  Getting
+            started </a></nav>
+        <p>See <a href="guide.html#install">the <b>guide</b></a> and
+        <a href="https://crates.io/crates/scraper">scraper</a>.</p>
+        <a href="guide.html">Guide again</a>
+        <a href="/about"><img src="logo.png"></a>
+        <a href="/about">About us</a>
+        <a href="mailto:team@example.com">Mail</a>
+        <a href="javascript:void(0)">Menu</a>
+        <a href="#top">Top</a>
+        </body></html>
+        "##;
+
+        let links = extract_links(html, "https://example.com/book/intro.html");
+        let pairs: Vec<(&str, &str)> = links
+            .iter()
+            .map(|l| (l.url.as_str(), l.text.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("https://example.com/", "Home"),
+                ("https://example.com/docs/", "Getting started"),
+                ("https://example.com/book/guide.html", "the guide"),
+                ("https://crates.io/crates/scraper", "scraper"),
+                ("https://example.com/about", "About us"),
+                ("https://example.com/book/intro.html", "Top"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_page_metadata_counts_words() {
+        let markdown = "# Title\n\n* one item\n* `code` here\n\n---\n\n**Bold** text, _too_.\n";
+        assert_eq!(
+            PageMetadata::from_markdown(markdown),
+            PageMetadata {
+                word_count: 8,
+                reading_time_seconds: 2,
+                final_url: None,
+                redirects: Vec::new(),
+                status: None,
+                content_type: None,
+                size_bytes: None,
+                title: None,
+            }
+        );
+
+        let long = "word ".repeat(1000);
+        assert_eq!(PageMetadata::from_markdown(&long).reading_time_seconds, 300);
+        assert_eq!(PageMetadata::from_markdown("").word_count, 0);
+    }
+
+    #[test]
+    fn test_redirect_target() {
+        assert_eq!(
+            redirect_target("https://example.com/a/b", "https://other.org/x").unwrap(),
+            "https://other.org/x"
+        );
+        assert_eq!(
+            redirect_target("https://example.com/a/b", "/login?next=%2Fa").unwrap(),
+            "https://example.com/login?next=%2Fa"
+        );
+        assert_eq!(
+            redirect_target("https://example.com/a/b", "c").unwrap(),
+            "https://example.com/a/c"
+        );
+        assert!(redirect_target("not a url", "/relative").is_err());
+    }
+
+    #[test]
+    fn test_fetch_response_headers_ignore_case() {
+        let response = FetchResponse {
+            url: "https://example.com/".into(),
+            status: 200,
+            headers: HashMap::from([(
+                "Content-Type".to_string(),
+                "text/html; charset=utf-8".to_string(),
+            )]),
+            body: String::new(),
+            binary: None,
+            redirects: Vec::new(),
+        };
+        assert_eq!(response.content_type(), Some("text/html; charset=utf-8"));
+        assert_eq!(
+            response.header("CONTENT-TYPE"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(response.header("location"), None);
+    }
+
+    #[test]
+    fn test_redirect_chain() {
+        let redirects = vec![
+            RedirectHop {
+                url: "http://example.com/".into(),
+                status: 301,
+            },
+            RedirectHop {
+                url: "https://example.com/".into(),
+                status: 302,
+            },
+        ];
+        assert_eq!(
+            describe_redirects(&redirects, "https://www.example.com/"),
+            "http://example.com/ (301) -> https://example.com/ (302) -> https://www.example.com/"
+        );
+        assert_eq!(describe_redirects(&[], "https://a/"), "https://a/");
+
+        let page = FetchResponse {
+            url: "https://www.example.com/".into(),
+            status: 200,
+            headers: HashMap::from([("Content-Type".to_string(), "text/html".to_string())]),
+            body: String::new(),
+            binary: None,
+            redirects: redirects.clone(),
+        };
+        let metadata = PageMetadata::from_markdown("text").with_response(&page, 512);
+        assert_eq!(
+            serde_json::to_value(&metadata).unwrap(),
+            serde_json::json!({
+                "word_count": 1,
+                "reading_time_seconds": 0,
+                "final_url": "https://www.example.com/",
+                "redirects": [
+                    { "url": "http://example.com/", "status": 301 },
+                    { "url": "https://example.com/", "status": 302 },
+                ],
+                "status": 200,
+                "content_type": "text/html",
+                "size_bytes": 512,
+            })
+        );
+
+        let direct = FetchResponse {
+            redirects: Vec::new(),
+            headers: HashMap::new(),
+            ..page
+        };
+        let metadata = PageMetadata::from_markdown("text").with_response(&direct, 0);
+        assert!(metadata.redirects.is_empty());
+        assert_eq!(metadata.content_type, None);
+    }
+
+    #[test]
+    fn test_redirect_chain_stops_at_a_loop() {
+        let mut current = "https://a.example/".to_string();
+        let mut chain = RedirectChain::new(&current, 10);
+        chain
+            .follow(&mut current, 301, "https://b.example/".into())
+            .unwrap();
+        assert_eq!(current, "https://b.example/");
+
+        let error = chain
+            .follow(&mut current, 302, "https://a.example/".into())
+            .unwrap_err();
+        assert_eq!(
+            error.message,
+            "Redirect loop detected at: https://a.example/ \
+             (https://a.example/ (301) -> https://b.example/ (302) -> https://a.example/)"
+        );
+        assert_eq!(
+            error.details,
+            Some(serde_json::json!({
+                "redirects": [
+                    { "url": "https://a.example/", "status": 301 },
+                    { "url": "https://b.example/", "status": 302 },
+                ],
+            }))
+        );
+    }
+
+    #[test]
+    fn test_redirect_chain_stops_after_max_redirects() {
+        let mut current = "https://example.com/0".to_string();
+        let mut chain = RedirectChain::new(&current, 2);
+        for hop in 1..=2 {
+            chain
+                .follow(&mut current, 302, format!("https://example.com/{}", hop))
+                .unwrap();
+        }
+
+        let error = chain
+            .follow(&mut current, 302, "https://example.com/3".into())
+            .unwrap_err();
+        assert_eq!(
+            error.message,
+            "Too many redirects (https://example.com/0 (302) -> https://example.com/1 (302) \
+             -> https://example.com/2 (302) -> https://example.com/3)"
+        );
+        assert_eq!(
+            error.details,
+            Some(serde_json::json!({
+                "max_redirects": 2,
+                "redirects": [
+                    { "url": "https://example.com/0", "status": 302 },
+                    { "url": "https://example.com/1", "status": 302 },
+                    { "url": "https://example.com/2", "status": 302 },
+                ],
+            }))
+        );
+    }
+
+    #[test]
+    fn test_extract_title() {
+        assert_eq!(
+            extract_title("<html><head><title>\n  Rust &amp;\n  WASM </title></head></html>"),
+            Some("Rust & WASM".to_string())
+        );
+        assert_eq!(
+            extract_title("<html><head><title> </title></head></html>"),
+            None
+        );
+        assert_eq!(extract_title("<p>No title</p>"), None);
+    }
+
+    #[test]
+    fn test_is_json_content_type() {
+        assert!(is_json_content_type("application/json"));
+        assert!(is_json_content_type("Application/JSON; charset=utf-8"));
+        assert!(is_json_content_type("application/problem+json"));
+        assert!(!is_json_content_type("text/html; charset=utf-8"));
+        assert!(!is_json_content_type("application/jsonp"));
+    }
+
+    #[test]
+    fn test_pretty_json() {
+        let body = r#"{"name":"hyper-mcp-search","stars":42,"tags":["search","browse"]}"#;
+        assert_eq!(
+            pretty_json(body).as_deref(),
+            Some(
+                "{\n  \"name\": \"hyper-mcp-search\",\n  \"stars\": 42,\n  \"tags\": [\n    \"search\",\n    \"browse\"\n  ]\n}"
+            )
+        );
+        assert_eq!(pretty_json("not json {"), None);
+    }
+
+    #[test]
+    fn test_renders_as_html() {
+        assert!(renders_as_html(Some("text/html; charset=utf-8"), "plain"));
+        assert!(renders_as_html(Some("application/xhtml+xml"), ""));
+        assert!(!renders_as_html(Some("text/plain"), "<b>not markup</b>"));
+        // Unknown or missing types are sniffed
+        assert!(renders_as_html(None, "  <!DOCTYPE html><html></html>"));
+        assert!(renders_as_html(Some("application/x-unknown"), "<p>hi</p>"));
+        assert!(!renders_as_html(None, "User-agent: *\nDisallow: /"));
+    }
+
+    #[test]
+    fn test_find_header_ignores_case() {
+        let headers = HashMap::from([
+            ("Location".to_string(), "/moved".to_string()),
+            ("content-TYPE".to_string(), "application/json".to_string()),
+            ("CONTENT-LENGTH".to_string(), "42".to_string()),
+        ]);
+
+        assert_eq!(find_header(&headers, "location"), Some("/moved"));
+        assert_eq!(find_header(&headers, "LOCATION"), Some("/moved"));
+        assert_eq!(
+            find_header(&headers, "Content-Type"),
+            Some("application/json")
+        );
+        assert_eq!(find_header(&headers, "content-length"), Some("42"));
+        assert_eq!(find_header(&headers, "retry-after"), None);
+    }
+
+    #[test]
+    fn test_html_to_markdown_keeps_code_languages() {
+        let html = r#"
+        <html><body>
+        <p>Python:</p>
+        <pre><code class="language-python">def greet(name):
+    print(f"Hello {name}")
+</code></pre>
+        <p>Rust:</p>
+        <pre class="lang-rust"><code>fn main() {
+    println!("Hello");
+}</code></pre>
+        <p>JavaScript:</p>
+        <div class="highlight highlight-source-js"><pre>const x = 1;</pre></div>
+        <p>Plain:</p>
+        <pre><code>no language here</code></pre>
+        <p>Inline <code class="language-sh">ls</code> code.</p>
+        <pre><code class="language-JavaScript">console.log(x);</code></pre>
+        </body></html>
+        "#;
+
+        let markdown = html_to_markdown(html);
+        assert!(markdown.contains("```python\ndef greet(name):"));
+        assert!(markdown.contains("```rust\nfn main() {"));
+        assert!(markdown.contains("```js\nconst x = 1;"));
+        assert!(markdown.contains("```\nno language here\n```"));
+        assert!(markdown.contains("```javascript\nconsole.log(x);"));
+        // Closing fences are left alone
+        assert_eq!(markdown.matches("```python").count(), 1);
+        assert!(!markdown.contains("```\n```"));
+    }
+
+    #[test]
+    fn test_generate_toc_nests_headings() {
+        let html = "<h1>Guide</h1><p>Intro</p><h2>Install</h2><h3>From source</h3>\
+            <h4>Linux</h4><h5>Debian</h5><h6>Bookworm</h6><h2>Usage</h2><h3>Linux</h3>";
+        let toc = generate_toc(&html_to_markdown(html));
+
+        let expected = [
+            "- [Guide](#guide)",
+            "  - [Install](#install)",
+            "    - [From source](#from-source)",
+            "      - [Linux](#linux)",
+            "        - [Debian](#debian)",
+            "          - [Bookworm](#bookworm)",
+            "  - [Usage](#usage)",
+            "    - [Linux](#linux-1)",
+        ];
+        assert_eq!(toc, expected.join("\n") + "\n");
+    }
+
+    #[test]
+    fn test_generate_toc_with_inline_code() {
+        let markdown = "## The `fetch` function ##\n\nText\n\n### Using C#\n\n\
+            ```\n# not a heading\n```\n\n#### `Result<T, E>` & errors\n\n\
+            - list item\n---\n";
+        let expected = [
+            "- [The `fetch` function](#the-fetch-function)",
+            "  - [Using C#](#using-c)",
+            "    - [`Result<T, E>` & errors](#resultt-e--errors)",
+        ];
+        assert_eq!(generate_toc(markdown), expected.join("\n") + "\n");
+        assert_eq!(generate_toc("No headings here.\n"), "");
+    }
+
+    #[test]
+    fn test_strip_landmarks_removes_navigation() {
+        let html = r#"
+        <html><body>
+        <header class="site"><a href="/">Logo</a><nav><a href="/blog">Blog</a></nav></header>
+        <nav aria-label="Breadcrumb"><a href="/docs">Docs</a></nav>
+        <main>
+        <article>
+        <header><h1>Release notes</h1></header>
+        <p>Version 2 is out.</p>
+        <footer>Posted by the team</footer>
+        </article>
+        </main>
+        <footer><a href="/privacy">Privacy</a></footer>
+        </body></html>
+        "#;
+
+        let markdown = html_to_markdown(&strip_landmarks(html));
+        assert!(markdown.contains("Release notes"));
+        assert!(markdown.contains("Version 2 is out."));
+        assert!(markdown.contains("Posted by the team"));
+        for boilerplate in ["Logo", "Blog", "Docs", "Privacy"] {
+            assert!(!markdown.contains(boilerplate), "{} was kept", boilerplate);
+        }
+    }
+
+    const MIXED_HTML: &str = r#"
+        <p>See the <a href="https://example.com/docs" title="Docs">documentation</a>
+        <img src="data:image/png;base64,iVBORw0KGgo=" alt="spacer">
+        and the <A HREF="/guide">guide<IMG SRC="/icon.svg"></A>.</p>
+        <figure><img src="/chart.png" alt="Chart"><figcaption>Growth</figcaption></figure>
+        <p><abbr title="HyperText">HTML</abbr> stays.</p>
+    "#;
+
+    #[test]
+    fn test_strip_images() {
+        let markdown = html2md::parse_html(&strip_images(MIXED_HTML));
+        assert!(!markdown.contains("!["));
+        assert!(!markdown.contains("data:image"));
+        assert!(markdown.contains("Growth"));
+        assert!(markdown.contains("[documentation](https://example.com/docs"));
+    }
+
+    #[test]
+    fn test_strip_links() {
+        let markdown = html2md::parse_html(&strip_links(MIXED_HTML));
+        assert!(!markdown.contains("](https://example.com/docs"));
+        assert!(!markdown.contains("](/guide"));
+        assert!(markdown.contains("![Chart](/chart.png)"));
+        assert!(markdown.contains("documentation"));
+        assert!(markdown.contains("guide"));
+        assert!(markdown.contains("HTML stays."));
+
+        let both = html2md::parse_html(&strip_links(&strip_images(MIXED_HTML)));
+        assert!(!both.contains("!["));
+        assert!(!both.contains("]("));
+        assert!(both.contains("See the documentation"));
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<html><head><title>Ignored</title></head><body>
+            <h1>Title</h1>
+            <p>First   paragraph with
+               <a href="/x" data-id="7">a link</a>.</p>
+            <ul><li>One</li><li>Two</li></ul>
+            <table><tr><td>a</td><td>b</td></tr><tr><td>c</td><td>d</td></tr></table>
+            <p>Line<br>break</p>
+        </body></html>"#;
+
+        assert_eq!(
+            html_to_text(html),
+            "Title\n\nFirst paragraph with a link.\n\nOne\nTwo\n\na b\nc d\n\nLine\nbreak"
+        );
+        assert_eq!(PageFormat::from_name("HTML"), Some(PageFormat::Html));
+        assert_eq!(PageFormat::from_name("pdf"), None);
+    }
+
+    #[test]
+    fn test_collapse_blank_lines() {
+        let markdown = "\n\n  Title\n==========\n\n\n\n\nFirst paragraph.\n  \n\t\n\n\
+            Second paragraph.\n\nThird.\nSame block.\n\n\n";
+        assert_eq!(
+            collapse_blank_lines(markdown),
+            "Title\n==========\n\nFirst paragraph.\n\nSecond paragraph.\n\nThird.\nSame block."
+        );
+
+        // Exactly three newlines collapse, a single blank line is kept as is
+        assert_eq!(collapse_blank_lines("a\n\n\nb"), "a\n\nb");
+        assert_eq!(collapse_blank_lines("a\n\nb"), "a\n\nb");
+        assert_eq!(collapse_blank_lines("a\nb"), "a\nb");
+        // Empty divs leave long runs, including indented blank lines
+        assert_eq!(
+            collapse_blank_lines(&format!("a{}b", "\n    ".repeat(12))),
+            "a\n\n    b"
+        );
+        assert_eq!(collapse_blank_lines(" \n\t\n\n "), "");
+    }
+
+    #[test]
+    fn test_authorization_for_stays_on_origin_host() {
+        let bearer = BrowseAuth::Bearer("secret".to_string());
+        let origin = "https://docs.internal.example/start";
+        let allowed = vec![
+            "docs.internal.example".to_string(),
+            "cdn.example.com".to_string(),
+        ];
+
+        assert_eq!(
+            authorization_for(origin, origin, Some(&bearer), &allowed).as_deref(),
+            Some("Bearer secret")
+        );
+        assert_eq!(
+            authorization_for(
+                "https://DOCS.internal.example/next",
+                origin,
+                Some(&bearer),
+                &allowed
+            )
+            .as_deref(),
+            Some("Bearer secret")
+        );
+
+        // Redirects that leave the original host (or port) drop the header
+        assert_eq!(
+            authorization_for(
+                "https://cdn.example.com/page",
+                origin,
+                Some(&bearer),
+                &allowed
+            ),
+            None
+        );
+        assert_eq!(
+            authorization_for(
+                "https://docs.internal.example:8443/",
+                origin,
+                Some(&bearer),
+                &allowed
+            ),
+            None
+        );
+        assert_eq!(authorization_for(origin, origin, None, &allowed), None);
+    }
+
+    #[test]
+    fn test_authorization_for_respects_allowed_hosts() {
+        let basic = BrowseAuth::Basic("user:pass".to_string());
+        let allowed = vec!["docs.internal.example".to_string()];
+
+        assert_eq!(
+            authorization_for(
+                "https://docs.internal.example/",
+                "https://docs.internal.example/",
+                Some(&basic),
+                &allowed
+            )
+            .as_deref(),
+            Some("Basic dXNlcjpwYXNz")
+        );
+        assert_eq!(
+            authorization_for(
+                "https://example.com/",
+                "https://example.com/",
+                Some(&basic),
+                &allowed
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_authorization_for_requires_allowed_hosts() {
+        let bearer = BrowseAuth::Bearer("secret".to_string());
+        let origin = "https://docs.internal.example/";
+        assert_eq!(authorization_for(origin, origin, Some(&bearer), &[]), None);
+    }
+
+    #[test]
+    fn test_check_domain_blocklist() {
+        let blocklist = vec!["example.com".to_string()];
+
+        let error = check_domain("https://www.Example.com/page", &[], &blocklist).unwrap_err();
+        assert_eq!(error.message, "Domain blocked: www.example.com");
+        assert!(check_domain("https://example.com", &[], &blocklist).is_err());
+        assert!(check_domain("https://notexample.com", &[], &blocklist).is_ok());
+
+        // The blocklist wins when a domain is on both lists
+        let allowlist = vec!["www.example.com".to_string()];
+        assert!(check_domain("https://www.example.com", &allowlist, &blocklist).is_err());
+    }
+
+    #[test]
+    fn test_check_domain_allowlist() {
+        let allowlist = vec!["docs.rs".to_string(), "rust-lang.org".to_string()];
+
+        assert!(check_domain("https://docs.rs/serde", &allowlist, &[]).is_ok());
+        assert!(check_domain("https://doc.rust-lang.org/std", &allowlist, &[]).is_ok());
+        let error = check_domain("https://crates.io", &allowlist, &[]).unwrap_err();
+        assert_eq!(error.message, "Domain not in allowlist");
+        assert!(check_domain("https://crates.io", &[], &[]).is_ok());
+    }
+
+    #[test]
+    fn test_validate_url() {
+        assert_eq!(validate_url("https://example.com/page"), Ok(()));
+        assert_eq!(validate_url("http://10.0.0.5:8080/"), Ok(()));
+
+        assert!(
+            validate_url("not a url")
+                .unwrap_err()
+                .starts_with("Invalid URL")
+        );
+        assert!(
+            validate_url("file:///etc/passwd")
+                .unwrap_err()
+                .starts_with("Unsupported URL scheme 'file'")
+        );
+        for local in [
+            "http://localhost:8080/",
+            "http://LOCALHOST/",
+            "http://127.0.0.1/admin",
+            "http://[::1]:3000/",
+        ] {
+            assert!(
+                validate_url(local)
+                    .unwrap_err()
+                    .starts_with("Refusing to browse local address"),
+                "{} accepted",
+                local
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_private_host_blocks_private_ranges() {
+        for private in [
+            "http://10.0.0.1/",
+            "http://10.255.255.255/",
+            "http://172.16.0.1/",
+            "http://172.31.255.254/",
+            "http://192.168.0.1/",
+            "http://192.168.255.255/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://127.8.0.1/",
+            "http://0.0.0.0/",
+            "http://[fd00::1]/",
+            "http://[fe80::1]/",
+            "http://[::ffff:10.0.0.1]/",
+            "http://intranet/",
+            "http://printer.local/",
+            "http://wiki.corp.internal/",
+        ] {
+            assert!(check_private_host(private).is_err(), "{} accepted", private);
+        }
+    }
+
+    #[test]
+    fn test_check_private_host_allows_public_hosts() {
+        for public in [
+            "https://example.com/",
+            "http://172.15.0.1/",
+            "http://172.32.0.1/",
+            "http://192.169.0.1/",
+            "http://8.8.8.8/",
+            "http://[2001:db8::1]/",
+        ] {
+            assert_eq!(check_private_host(public), Ok(()), "{} rejected", public);
+        }
+    }
+
+    #[test]
+    fn test_rate_limited_error() {
+        let headers = HashMap::from([("Retry-After".to_string(), "120".to_string())]);
+        let error = rate_limited_error("https://example.com/page", &headers);
+        assert_eq!(error.kind, ErrorKind::RateLimited);
+        assert_eq!(
+            error.message,
+            "example.com rate limited this client, retry in 120s"
+        );
+        assert_eq!(
+            error.details,
+            Some(serde_json::json!({ "retry_after_seconds": 120 }))
+        );
+
+        let error = rate_limited_error("https://example.com/page", &HashMap::new());
+        assert_eq!(
+            error.message,
+            "example.com rate limited this client, retry later"
+        );
+        assert_eq!(error.details, None);
+    }
+
+    #[test]
+    fn test_redirect_not_followed_error() {
+        let error = redirect_not_followed_error(301, "https://example.com/");
+        assert_eq!(error.kind, ErrorKind::HttpStatus);
+        assert_eq!(
+            error.message,
+            "HTTP 301 redirect to https://example.com/ was not followed: browse that URL directly or enable follow_redirects"
+        );
+        assert_eq!(
+            error.details,
+            Some(serde_json::json!({ "status": 301, "location": "https://example.com/" }))
+        );
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(Some("application/pdf"), b"%PDF-1.7"));
+        assert!(is_binary(Some("Image/PNG"), b"\x89PNG"));
+        assert!(is_binary(
+            Some("application/octet-stream; name=x"),
+            b"plain"
+        ));
+        assert!(!is_binary(Some("image/svg+xml"), b"<svg></svg>"));
+        assert!(!is_binary(Some("text/html"), b"<p>hi</p>"));
+
+        // Mislabelled or unlabelled bodies are sniffed for null bytes
+        assert!(is_binary(Some("text/html"), b"PK\x03\x04\x00\x00"));
+        assert!(is_binary(None, b"\x00\x01\x02"));
+        assert!(!is_binary(None, b"\xFF\xFEh\x00i\x00"));
+        let mut late_null = vec![b'a'; BINARY_SNIFF_BYTES];
+        late_null.push(0);
+        assert!(!is_binary(None, &late_null));
+    }
+
+    #[test]
+    fn test_binary_summary() {
+        let summary: serde_json::Value = serde_json::from_str(&binary_summary(
+            "https://example.com/a.pdf",
+            "application/pdf",
+            2048,
+        ))
+        .unwrap();
+        assert_eq!(summary["binary"], true);
+        assert_eq!(summary["url"], "https://example.com/a.pdf");
+        assert_eq!(summary["content_type"], "application/pdf");
+        assert_eq!(summary["content_length"], 2048);
+        assert!(summary["note"].as_str().unwrap().contains("return_binary"));
+    }
+
+    #[test]
+    fn test_check_response_size() {
+        assert!(check_response_size(10, 10).is_ok());
+        let error = check_response_size(11, 10).unwrap_err();
+        assert_eq!(
+            error.message,
+            "Response too large: 11 bytes exceeds limit of 10 bytes"
+        );
+        assert_eq!(
+            error.details,
+            Some(serde_json::json!({ "size": 11, "limit": 10 }))
+        );
+    }
+
+    #[test]
+    fn test_http_status_error_401() {
+        let headers = HashMap::from([(
+            "WWW-Authenticate".to_string(),
+            "Basic realm=\"docs\"".to_string(),
+        )]);
+        let error = http_status_error(401, &headers, "<html>Unauthorized</html>");
+        assert_eq!(error.kind, ErrorKind::HttpStatus);
+        assert_eq!(
+            error.message,
+            "Authentication required: the resource needs credentials (WWW-Authenticate: Basic realm=\"docs\")"
+        );
+        assert_eq!(error.details, Some(serde_json::json!({ "status": 401 })));
+
+        let error = http_status_error(401, &HashMap::new(), "");
+        assert_eq!(
+            error.message,
+            "Authentication required: the resource needs credentials"
+        );
+    }
+
+    #[test]
+    fn test_http_status_error_403() {
+        let error = http_status_error(403, &HashMap::new(), "<html>Forbidden</html>");
+        assert_eq!(
+            error.message,
+            "Access forbidden: the server rejected the request"
+        );
+        assert_eq!(error.details, Some(serde_json::json!({ "status": 403 })));
+
+        let error = http_status_error(500, &HashMap::new(), "oops");
+        assert_eq!(error.message, "HTTP Error: 500 - oops");
+    }
+
+    #[test]
+    fn test_normalize_url_adds_missing_scheme() {
+        assert_eq!(
+            normalize_url("example.com/page").as_deref(),
+            Ok("https://example.com/page")
+        );
+        assert_eq!(
+            normalize_url("  //docs.rs/serde ").as_deref(),
+            Ok("https://docs.rs/serde")
+        );
+        assert_eq!(
+            normalize_url("localhost:8080/admin").as_deref(),
+            Ok("https://localhost:8080/admin")
+        );
+        assert_eq!(
+            normalize_url("http://example.com").as_deref(),
+            Ok("http://example.com/")
+        );
+    }
+
+    #[test]
+    fn test_normalize_url_rejects_invalid_input() {
+        assert_eq!(normalize_url("   ").unwrap_err(), "URL is empty");
+        assert_eq!(
+            normalize_url("http://exa mple.com").unwrap_err(),
+            "Invalid URL 'http://exa mple.com': invalid international domain name"
+        );
+        assert!(
+            normalize_url("example.com:99999")
+                .unwrap_err()
+                .starts_with("Invalid URL 'example.com:99999'")
+        );
+    }
 }
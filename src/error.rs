@@ -0,0 +1,328 @@
+use crate::browse::PaywallDetected;
+use crate::pdk::types::{CallToolResult, Content, ContentType};
+use crate::searxng::SearchError;
+use extism_pdk::config;
+use serde::{Serialize, Serializer};
+use serde_json::{Value, json};
+
+/// Category of a tool failure, serialized as `error.kind`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The remote server could not be reached
+    Connection,
+    /// The remote server answered with a non-success status
+    HttpStatus,
+    /// The response could not be decoded or parsed
+    Parse,
+    /// A tool argument is missing or invalid
+    InvalidArgument,
+    /// The requested tool does not exist
+    UnknownTool,
+    /// SearXNG answered but its engines failed to produce results
+    UpstreamEngine,
+    /// The browsed page appears to be behind a paywall
+    Paywall,
+    /// The remote server asked the client to slow down
+    RateLimited,
+    /// The remote server did not answer in time
+    Timeout,
+    /// Anything that does not fit the other kinds
+    Internal,
+}
+
+/// Coarse, stable error code for clients to switch on, serialized as `error.code`.
+///
+/// `ErrorKind` says where a failure happened; the code says what a client can do about it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    ConnectionFailed,
+    ParseError,
+    InvalidArgument,
+    NotFound,
+    RateLimited,
+    Timeout,
+    Unknown,
+}
+
+/// Error returned to MCP clients from a tool call
+#[derive(Debug)]
+pub struct ToolError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+impl ToolError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// Stable code for this error, refined by the HTTP status where one is known
+    pub fn code(&self) -> ErrorCode {
+        match self.kind {
+            ErrorKind::Connection => ErrorCode::ConnectionFailed,
+            ErrorKind::Parse => ErrorCode::ParseError,
+            ErrorKind::InvalidArgument => ErrorCode::InvalidArgument,
+            ErrorKind::UnknownTool => ErrorCode::NotFound,
+            ErrorKind::RateLimited => ErrorCode::RateLimited,
+            ErrorKind::Timeout => ErrorCode::Timeout,
+            ErrorKind::HttpStatus => {
+                let status = self
+                    .details
+                    .as_ref()
+                    .and_then(|d| d.get("status"))
+                    .and_then(Value::as_u64);
+                match status {
+                    Some(404) | Some(410) => ErrorCode::NotFound,
+                    Some(408) | Some(504) => ErrorCode::Timeout,
+                    Some(429) => ErrorCode::RateLimited,
+                    _ => ErrorCode::Unknown,
+                }
+            }
+            ErrorKind::UpstreamEngine | ErrorKind::Paywall | ErrorKind::Internal => {
+                ErrorCode::Unknown
+            }
+        }
+    }
+
+    /// Prefix the message with what the tool was doing when it failed
+    pub fn context(mut self, context: &str) -> Self {
+        self.message = format!("{}: {}", context, self.message);
+        self
+    }
+
+    /// Build the error result, as JSON unless `ERROR_FORMAT` is set to `text`
+    pub fn into_call_result(self) -> CallToolResult {
+        let plain_text = config::get("ERROR_FORMAT")
+            .ok()
+            .flatten()
+            .map(|s| s.eq_ignore_ascii_case("text"))
+            .unwrap_or(false);
+        self.to_call_result(plain_text)
+    }
+
+    fn to_call_result(&self, plain_text: bool) -> CallToolResult {
+        let (text, mime_type) = if plain_text {
+            (self.message.clone(), None)
+        } else {
+            (
+                json!({ "error": self }).to_string(),
+                Some("application/json".to_string()),
+            )
+        };
+
+        CallToolResult {
+            is_error: Some(true),
+            content: vec![Content {
+                annotations: None,
+                text: Some(text),
+                mime_type,
+                r#type: ContentType::Text,
+                data: None,
+            }],
+        }
+    }
+}
+
+impl Serialize for ToolError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Payload<'a> {
+            code: ErrorCode,
+            kind: ErrorKind,
+            message: &'a str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            details: Option<&'a Value>,
+        }
+
+        Payload {
+            code: self.code(),
+            kind: self.kind,
+            message: &self.message,
+            details: self.details.as_ref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl std::fmt::Display for ToolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+impl From<anyhow::Error> for ToolError {
+    fn from(error: anyhow::Error) -> Self {
+        if error.is::<PaywallDetected>() {
+            return ToolError::new(ErrorKind::Paywall, error.to_string());
+        }
+        match error.downcast::<ToolError>() {
+            Ok(tool_error) => tool_error,
+            Err(other) => ToolError::new(ErrorKind::Internal, other.to_string()),
+        }
+    }
+}
+
+impl From<SearchError> for ToolError {
+    fn from(error: SearchError) -> Self {
+        let message = error.to_string();
+        match error {
+            SearchError::Connection(_) => ToolError::new(ErrorKind::Connection, message),
+            SearchError::HttpStatus(status, _) => ToolError::new(ErrorKind::HttpStatus, message)
+                .with_details(json!({ "status": status })),
+            SearchError::Parse(_) => ToolError::new(ErrorKind::Parse, message),
+            SearchError::EmptyResults(unresponsive_engines) => {
+                ToolError::new(ErrorKind::UpstreamEngine, message)
+                    .with_details(json!({ "unresponsive_engines": unresponsive_engines }))
+            }
+            SearchError::InvalidArgument(_) => ToolError::new(ErrorKind::InvalidArgument, message),
+            SearchError::RateLimited(retry_after) => {
+                ToolError::new(ErrorKind::RateLimited, message)
+                    .with_details(json!({ "retry_after_seconds": retry_after }))
+            }
+            SearchError::Timeout(_) => ToolError::new(ErrorKind::Timeout, message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn test_tool_error_serializes_as_json_payload() {
+        let error = ToolError::new(ErrorKind::HttpStatus, "HTTP Error: 502")
+            .with_details(json!({ "status": 502 }));
+        let result = error.to_call_result(false);
+
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(
+            result.content[0].mime_type.as_deref(),
+            Some("application/json")
+        );
+        let payload: Value =
+            serde_json::from_str(result.content[0].text.as_ref().unwrap()).unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "error": {
+                    "code": "unknown",
+                    "kind": "http_status",
+                    "message": "HTTP Error: 502",
+                    "details": { "status": 502 },
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_tool_error_plain_text_mode() {
+        let result =
+            ToolError::new(ErrorKind::UnknownTool, "Unknown tool: foo").to_call_result(true);
+
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(result.content[0].mime_type, None);
+        assert_eq!(result.content[0].text.as_deref(), Some("Unknown tool: foo"));
+    }
+
+    #[test]
+    fn test_tool_error_from_anyhow() {
+        let wrapped: ToolError =
+            anyhow::Error::from(ToolError::new(ErrorKind::Parse, "bad json")).into();
+        assert_eq!(wrapped.kind, ErrorKind::Parse);
+        assert_eq!(wrapped.message, "bad json");
+
+        let paywall: ToolError = anyhow::Error::from(PaywallDetected).into();
+        assert_eq!(paywall.kind, ErrorKind::Paywall);
+
+        let other: ToolError = anyhow!("boom").into();
+        assert_eq!(other.kind, ErrorKind::Internal);
+        assert_eq!(
+            other.context("Search failed").message,
+            "Search failed: boom"
+        );
+    }
+
+    #[test]
+    fn test_tool_error_from_search_error() {
+        let status: ToolError =
+            SearchError::HttpStatus(502, "HTTP Error: 502 - Bad Gateway".into()).into();
+        assert_eq!(status.kind, ErrorKind::HttpStatus);
+        assert_eq!(status.message, "HTTP Error: 502 - Bad Gateway");
+        assert_eq!(status.details, Some(json!({ "status": 502 })));
+
+        let empty: ToolError =
+            SearchError::EmptyResults(vec![vec!["google".to_string(), "timeout".to_string()]])
+                .into();
+        assert_eq!(empty.kind, ErrorKind::UpstreamEngine);
+        assert_eq!(empty.message, "All search engines failed to respond");
+        assert_eq!(
+            empty.details,
+            Some(json!({ "unresponsive_engines": [["google", "timeout"]] }))
+        );
+
+        let connection: ToolError = SearchError::Connection("refused".into()).into();
+        assert_eq!(connection.kind, ErrorKind::Connection);
+        let parse: ToolError = SearchError::Parse("bad".into()).into();
+        assert_eq!(parse.kind, ErrorKind::Parse);
+        let argument: ToolError = SearchError::InvalidArgument("cursor".into()).into();
+        assert_eq!(argument.kind, ErrorKind::InvalidArgument);
+        let timeout: ToolError = SearchError::Timeout("slow".into()).into();
+        assert_eq!(timeout.code(), ErrorCode::Timeout);
+
+        let rate_limited: ToolError = SearchError::RateLimited(12).into();
+        assert_eq!(
+            json!(rate_limited),
+            json!({
+                "code": "rate_limited",
+                "kind": "rate_limited",
+                "message": "SearXNG rate limited this client, retry in 12s",
+                "details": { "retry_after_seconds": 12 },
+            })
+        );
+    }
+
+    #[test]
+    fn test_tool_error_codes() {
+        let code = |kind, details: Option<Value>| {
+            let mut error = ToolError::new(kind, "failed");
+            error.details = details;
+            error.code()
+        };
+
+        assert_eq!(
+            code(ErrorKind::Connection, None),
+            ErrorCode::ConnectionFailed
+        );
+        assert_eq!(code(ErrorKind::Parse, None), ErrorCode::ParseError);
+        assert_eq!(code(ErrorKind::UnknownTool, None), ErrorCode::NotFound);
+        assert_eq!(code(ErrorKind::Internal, None), ErrorCode::Unknown);
+        assert_eq!(
+            code(ErrorKind::HttpStatus, Some(json!({ "status": 404 }))),
+            ErrorCode::NotFound
+        );
+        assert_eq!(
+            code(ErrorKind::HttpStatus, Some(json!({ "status": 504 }))),
+            ErrorCode::Timeout
+        );
+        assert_eq!(code(ErrorKind::HttpStatus, None), ErrorCode::Unknown);
+
+        let payload = json!(ToolError::new(ErrorKind::InvalidArgument, "bad"));
+        assert_eq!(payload["code"], "invalid_argument");
+        assert!(payload.get("details").is_none());
+    }
+}
@@ -0,0 +1,376 @@
+use crate::browse::fetch;
+use crate::error::{ErrorKind, ToolError};
+use crate::trace::info;
+use anyhow::Result;
+use quick_xml::Reader;
+use quick_xml::events::{BytesStart, Event};
+use scraper::{Html, Selector};
+use serde::Serialize;
+use url::Url;
+
+/// Content types served for RSS and Atom feeds
+const FEED_CONTENT_TYPES: &[&str] = &[
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/xml",
+    "text/xml",
+];
+
+/// Single item of an RSS or Atom feed
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct FeedEntry {
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub published: Option<String>,
+    pub summary: Option<String>,
+}
+
+/// Field of the current entry that element text is collected into
+#[derive(Clone, Copy)]
+enum Field {
+    Title,
+    Link,
+    Published,
+    Summary,
+}
+
+fn entry_field(name: &[u8]) -> Option<Field> {
+    match name {
+        b"title" => Some(Field::Title),
+        b"link" => Some(Field::Link),
+        b"pubDate" | b"published" | b"updated" | b"date" => Some(Field::Published),
+        b"description" | b"summary" | b"content" => Some(Field::Summary),
+        _ => None,
+    }
+}
+
+/// Atom links carry the URL in `href`; only the main (alternate) link is kept
+fn atom_link(element: &BytesStart) -> Option<String> {
+    let mut href = None;
+    let mut alternate = true;
+    for attr in element.attributes().flatten() {
+        let value = attr.unescape_value().ok()?.to_string();
+        match attr.key.local_name().as_ref() {
+            b"href" => href = Some(value),
+            b"rel" => alternate = value == "alternate",
+            _ => {}
+        }
+    }
+    href.filter(|_| alternate)
+}
+
+fn set_field(entry: &mut FeedEntry, field: Field, value: String) {
+    let value = value.trim();
+    if value.is_empty() {
+        return;
+    }
+    let slot = match field {
+        Field::Title => &mut entry.title,
+        Field::Link => &mut entry.link,
+        Field::Published => &mut entry.published,
+        Field::Summary => &mut entry.summary,
+    };
+    // Keep the first value, e.g. `published` over a later `updated`
+    if slot.is_none() {
+        *slot = Some(value.to_string());
+    }
+}
+
+/// Whether a `Content-Type` header announces a feed (or XML that may be one)
+pub fn declares_feed(content_type: &str) -> bool {
+    let content_type = content_type.to_lowercase();
+    FEED_CONTENT_TYPES
+        .iter()
+        .any(|t| content_type.starts_with(t))
+}
+
+/// Render feed entries as a Markdown list: linked title, date, then the summary as plain text
+pub fn feed_markdown(entries: &[FeedEntry]) -> String {
+    let mut markdown = String::new();
+    for entry in entries {
+        let title = entry.title.as_deref().unwrap_or("Untitled");
+        match &entry.link {
+            Some(link) => markdown.push_str(&format!("- [{}]({})", title, link)),
+            None => markdown.push_str(&format!("- {}", title)),
+        }
+        if let Some(published) = &entry.published {
+            markdown.push_str(&format!(" ({})", published));
+        }
+        markdown.push('\n');
+
+        let summary = entry
+            .summary
+            .as_deref()
+            .map(|summary| {
+                Html::parse_fragment(summary)
+                    .root_element()
+                    .text()
+                    .collect::<String>()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_default();
+        if !summary.is_empty() {
+            markdown.push_str(&format!("  {}\n", summary));
+        }
+    }
+    markdown
+}
+
+/// Whether the document's root element is an RSS, RDF or Atom feed
+pub fn is_feed(xml: &str) -> bool {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => {
+                return matches!(e.local_name().as_ref(), b"rss" | b"feed" | b"RDF");
+            }
+            Ok(Event::Eof) | Err(_) => return false,
+            _ => {}
+        }
+    }
+}
+
+/// Parse the entries of an RSS 2.0, RSS 1.0 (RDF) or Atom feed
+pub fn parse_feed(xml: &str) -> Result<Vec<FeedEntry>> {
+    let mut reader = Reader::from_str(xml);
+    let mut entries = Vec::new();
+    let mut current: Option<FeedEntry> = None;
+    let mut field: Option<Field> = None;
+    let mut text = String::new();
+
+    loop {
+        let event = reader.read_event().map_err(|e| {
+            ToolError::new(
+                ErrorKind::Parse,
+                format!(
+                    "Failed to parse feed at position {}: {}",
+                    reader.error_position(),
+                    e
+                ),
+            )
+        })?;
+
+        match event {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"item" | b"entry" => current = Some(FeedEntry::default()),
+                name if current.is_some() && field.is_none() => {
+                    field = entry_field(name);
+                    text.clear();
+                    if let (Some(Field::Link), Some(entry)) = (field, current.as_mut())
+                        && let Some(href) = atom_link(&e)
+                    {
+                        set_field(entry, Field::Link, href);
+                    }
+                }
+                _ => {}
+            },
+            Event::Empty(e) => {
+                if let Some(entry) = current.as_mut()
+                    && e.local_name().as_ref() == b"link"
+                    && let Some(href) = atom_link(&e)
+                {
+                    set_field(entry, Field::Link, href);
+                }
+            }
+            Event::Text(e) if field.is_some() => {
+                text.push_str(&e.unescape().unwrap_or_default());
+            }
+            Event::CData(e) if field.is_some() => {
+                text.push_str(&String::from_utf8_lossy(&e));
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"item" | b"entry" => {
+                    if let Some(entry) = current.take() {
+                        entries.push(entry);
+                    }
+                    field = None;
+                }
+                name => {
+                    if let (Some(f), Some(entry)) = (field, current.as_mut())
+                        && entry_field(name).is_some()
+                    {
+                        set_field(entry, f, std::mem::take(&mut text));
+                        field = None;
+                    }
+                }
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Find feed URLs advertised by an HTML page via `<link rel="alternate">`
+pub fn discover_feed_links(html: &str, base_url: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let links = Selector::parse("link[rel][type][href]").unwrap();
+    let base = Url::parse(base_url).ok();
+
+    document
+        .select(&links)
+        .filter(|link| {
+            let rel = link.value().attr("rel").unwrap_or_default().to_lowercase();
+            let link_type = link.value().attr("type").unwrap_or_default().to_lowercase();
+            rel.split_whitespace().any(|r| r == "alternate")
+                && (link_type == "application/rss+xml" || link_type == "application/atom+xml")
+        })
+        .filter_map(|link| {
+            let href = link.value().attr("href")?;
+            match &base {
+                Some(base) => base.join(href).ok().map(|u| u.to_string()),
+                None => Some(href.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Fetch a feed and parse its entries. HTML pages are searched for an advertised feed,
+/// which is then fetched instead.
+pub fn fetch_feed(url: &str) -> Result<Vec<FeedEntry>> {
+    let page = fetch(url)?;
+
+    let declared_feed = page.content_type().is_some_and(declares_feed);
+    if declared_feed || is_feed(&page.body) {
+        return parse_feed(&page.body);
+    }
+
+    let feed_url = discover_feed_links(&page.body, &page.url)
+        .into_iter()
+        .next()
+        .ok_or_else(|| {
+            ToolError::new(
+                ErrorKind::Parse,
+                format!("No RSS or Atom feed found at {}", page.url),
+            )
+        })?;
+    info!("Discovered feed: {}", feed_url);
+
+    let feed = fetch(&feed_url)?;
+    if !is_feed(&feed.body) {
+        return Err(ToolError::new(
+            ErrorKind::Parse,
+            format!("{} is not an RSS or Atom feed", feed_url),
+        )
+        .into());
+    }
+    parse_feed(&feed.body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+    <rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+      <channel>
+        <title>Example blog</title>
+        <link>https://example.com/</link>
+        <item>
+          <title>First &amp; foremost</title>
+          <link>https://example.com/first</link>
+          <pubDate>Mon, 01 Jan 2024 10:00:00 GMT</pubDate>
+          <description><![CDATA[<p>Hello <b>world</b></p>]]></description>
+        </item>
+        <item>
+          <title>Second</title>
+          <link>https://example.com/second</link>
+          <dc:date>2024-01-02</dc:date>
+        </item>
+      </channel>
+    </rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+    <feed xmlns="http://www.w3.org/2005/Atom">
+      <title>Example feed</title>
+      <link href="https://example.org/"/>
+      <entry>
+        <title type="html">Atom entry</title>
+        <link rel="edit" href="https://example.org/edit/1"/>
+        <link rel="alternate" href="https://example.org/2003/12/13/atom03"/>
+        <published>2003-12-13T08:29:29-04:00</published>
+        <updated>2003-12-14T18:30:02Z</updated>
+        <summary>Some text.</summary>
+      </entry>
+    </feed>"#;
+
+    #[test]
+    fn test_parse_rss_feed() {
+        assert!(is_feed(RSS));
+        let entries = parse_feed(RSS).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0],
+            FeedEntry {
+                title: Some("First & foremost".into()),
+                link: Some("https://example.com/first".into()),
+                published: Some("Mon, 01 Jan 2024 10:00:00 GMT".into()),
+                summary: Some("<p>Hello <b>world</b></p>".into()),
+            }
+        );
+        assert_eq!(entries[1].published.as_deref(), Some("2024-01-02"));
+        assert_eq!(entries[1].summary, None);
+    }
+
+    #[test]
+    fn test_parse_atom_feed() {
+        assert!(is_feed(ATOM));
+        let entries = parse_feed(ATOM).unwrap();
+        assert_eq!(
+            entries,
+            vec![FeedEntry {
+                title: Some("Atom entry".into()),
+                link: Some("https://example.org/2003/12/13/atom03".into()),
+                published: Some("2003-12-13T08:29:29-04:00".into()),
+                summary: Some("Some text.".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_feed_markdown() {
+        let entries = parse_feed(RSS).unwrap();
+        assert_eq!(
+            feed_markdown(&entries),
+            "- [First & foremost](https://example.com/first) (Mon, 01 Jan 2024 10:00:00 GMT)\n  Hello world\n- [Second](https://example.com/second) (2024-01-02)\n"
+        );
+
+        let bare = FeedEntry {
+            title: None,
+            link: None,
+            published: None,
+            summary: None,
+        };
+        assert_eq!(feed_markdown(&[bare]), "- Untitled\n");
+        assert!(declares_feed("application/rss+xml; charset=utf-8"));
+        assert!(!declares_feed("text/html"));
+    }
+
+    #[test]
+    fn test_is_feed_rejects_html() {
+        assert!(!is_feed("<!DOCTYPE html><html><body>Hi</body></html>"));
+        assert!(!is_feed("not xml at all"));
+    }
+
+    #[test]
+    fn test_discover_feed_links() {
+        let html = r#"
+        <html><head>
+        <link rel="stylesheet" type="text/css" href="/style.css">
+        <link rel="alternate" type="application/rss+xml" title="RSS" href="/feed.xml">
+        <link rel="alternate" type="application/atom+xml" href="https://example.com/atom">
+        </head><body></body></html>
+        "#;
+
+        assert_eq!(
+            discover_feed_links(html, "https://example.com/blog/"),
+            vec![
+                "https://example.com/feed.xml".to_string(),
+                "https://example.com/atom".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,455 @@
+use crate::searxng::SearchResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Characters kept free for the truncation notice when a response is over budget
+const TRUNCATION_NOTE_RESERVE: usize = 100;
+
+/// Output format of the search tool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    Citations,
+    Markdown,
+    /// Numbered list of bold titles, links and quoted snippets
+    List,
+}
+
+impl OutputFormat {
+    pub const NAMES: &'static [&'static str] = &["json", "citations", "markdown", "list"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "citations" => Some(Self::Citations),
+            "markdown" => Some(Self::Markdown),
+            "list" => Some(Self::List),
+            _ => None,
+        }
+    }
+}
+
+/// Render results as numbered sources for citation, followed by a JSON block
+/// mapping each reference number to its URL.
+///
+/// Numbers start at `first_index` (1 for a new search) so that follow-up pages
+/// and tools that reuse the same results keep the same reference IDs.
+pub fn citations_markdown(
+    results: &[SearchResult],
+    first_index: usize,
+    next_cursor: Option<&str>,
+) -> String {
+    let mut out = String::from("Cite sources by their reference number, e.g. [1].\n\n");
+    let mut references = Map::new();
+
+    for (i, result) in results.iter().enumerate() {
+        let index = first_index + i;
+        out.push_str(&format!("[{}] {}\n{}\n", index, result.title, result.url));
+        if !result.content.trim().is_empty() {
+            out.push_str(result.content.trim());
+            out.push('\n');
+        }
+        out.push('\n');
+        references.insert(index.to_string(), Value::String(result.url.clone()));
+    }
+
+    out.push_str("```json\n");
+    out.push_str(&Value::Object(references).to_string());
+    out.push_str("\n```\n");
+
+    if let Some(cursor) = next_cursor {
+        out.push_str(&more_results_line(cursor));
+    }
+
+    out
+}
+
+/// Render each result as a linked heading with its category and snippet
+pub fn results_markdown(results: &[SearchResult], next_cursor: Option<&str>) -> String {
+    let mut out = String::new();
+
+    for result in results {
+        out.push_str(&format!(
+            "## [{}]({})\n*{}*\n\n",
+            result.title, result.url, result.category
+        ));
+        if !result.content.trim().is_empty() {
+            out.push_str(result.content.trim());
+            out.push_str("\n\n");
+        }
+        out.push_str("---\n\n");
+    }
+
+    if let Some(cursor) = next_cursor {
+        out.push_str(&more_results_line(cursor));
+    }
+
+    out
+}
+
+pub fn more_results_line(cursor: &str) -> String {
+    format!("\nMore results: search again with cursor `{}`\n", cursor)
+}
+
+/// "Related searches" block listing SearXNG's query suggestions, if it has any
+pub fn related_searches_markdown(suggestions: &[String]) -> Option<String> {
+    if suggestions.is_empty() {
+        return None;
+    }
+    let mut out = String::from("Related searches\n\n");
+    for suggestion in suggestions {
+        out.push_str(&format!("- {}\n", suggestion));
+    }
+    Some(out)
+}
+
+/// Notices for text output formats about engines that did not respond and other warnings,
+/// empty when there are none
+pub fn warnings_note(warnings: &[String], unresponsive_engines: &[Vec<String>]) -> String {
+    let mut out = String::new();
+    if !unresponsive_engines.is_empty() {
+        let engines: Vec<String> = unresponsive_engines
+            .iter()
+            .map(|pair| match pair.as_slice() {
+                [engine, reason, ..] => format!("{} ({})", engine, reason),
+                [engine] => engine.clone(),
+                [] => "unknown".to_string(),
+            })
+            .collect();
+        out.push_str(&format!(
+            "\nSome engines did not respond, results may be incomplete: {}\n",
+            engines.join(", ")
+        ));
+    }
+    for warning in warnings {
+        out.push_str(&format!("\nWarning: {}\n", warning));
+    }
+    out
+}
+
+/// Message returned instead of an empty result list, so it is not mistaken for a failure
+pub fn no_results_message(query: &str, suggestions: &[String]) -> String {
+    let mut message = format!("No results found for \"{}\"", query);
+    if !suggestions.is_empty() {
+        message.push_str(&format!(". Try: {}", suggestions.join(", ")));
+    }
+    message
+}
+
+/// How much was cut to fit a response into its character budget
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Truncation {
+    pub dropped_results: usize,
+    pub dropped_chars: usize,
+}
+
+impl Truncation {
+    /// One-line notice for text output formats
+    pub fn note(&self) -> String {
+        format!(
+            "\nOutput truncated to fit max_chars: {} results and {} characters dropped\n",
+            self.dropped_results, self.dropped_chars
+        )
+    }
+}
+
+/// Shorten `text` to at most `max_chars` characters, ending with `…`.
+///
+/// Cuts after the last sentence in the second half of the allowance, otherwise at the last
+/// word boundary. Counts characters, so multi-byte text is never split.
+pub fn trim_snippet(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+
+    // Leave room for the ellipsis
+    let end = text
+        .char_indices()
+        .nth(max_chars - 1)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+    let head = &text[..end];
+
+    let mut sentence_end = None;
+    let mut chars = head.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let followed_by_space = chars.peek().is_some_and(|(_, next)| next.is_whitespace());
+        if matches!(c, '.' | '!' | '?') && followed_by_space {
+            sentence_end = Some(i + c.len_utf8());
+        }
+    }
+
+    match sentence_end.filter(|&i| i >= head.len() / 2) {
+        Some(i) => format!("{} …", &head[..i]),
+        None => {
+            let cut = head.rfind(char::is_whitespace).unwrap_or(head.len());
+            format!("{}…", head[..cut].trim_end())
+        }
+    }
+}
+
+/// Cut `text` to at most `max_bytes` bytes, backing off to a character boundary.
+/// Returns whether anything was cut.
+pub fn truncate_to_bytes(text: &mut String, max_bytes: usize) -> bool {
+    if text.len() <= max_bytes {
+        return false;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    true
+}
+
+/// Fit the rendered output of `results` into `max_chars` characters.
+///
+/// Snippets are trimmed first, all to the same largest length that fits; trailing results
+/// are dropped only if the output is still too long with empty snippets. The first result
+/// is always kept. Returns `None` when the output already fits.
+pub fn fit_to_budget(
+    results: &mut Vec<SearchResult>,
+    max_chars: usize,
+    render: impl Fn(&[SearchResult]) -> String,
+) -> Option<Truncation> {
+    let original_chars = render(results).chars().count();
+    if original_chars <= max_chars {
+        return None;
+    }
+    let budget = max_chars.saturating_sub(TRUNCATION_NOTE_RESERVE);
+
+    let snippets: Vec<String> = results.iter().map(|r| r.content.clone()).collect();
+    let rendered_chars = |results: &mut Vec<SearchResult>, cap: usize| {
+        for (result, snippet) in results.iter_mut().zip(&snippets) {
+            result.content = trim_snippet(snippet, cap);
+        }
+        render(results).chars().count()
+    };
+
+    let mut dropped_results = 0;
+    while results.len() > 1 && rendered_chars(results, 0) > budget {
+        results.pop();
+        dropped_results += 1;
+    }
+
+    // Largest snippet length that still fits
+    let (mut low, mut high) = (
+        0,
+        snippets
+            .iter()
+            .map(|s| s.chars().count())
+            .max()
+            .unwrap_or(0),
+    );
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if rendered_chars(results, mid) <= budget {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    let final_chars = rendered_chars(results, low);
+
+    Some(Truncation {
+        dropped_results,
+        dropped_chars: original_chars.saturating_sub(final_chars),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_citations_markdown() {
+        let results = vec![
+            SearchResult::for_test(
+                "Rust",
+                "https://www.rust-lang.org/",
+                "A language empowering everyone.",
+            ),
+            SearchResult::for_test("Crates", "https://crates.io/", ""),
+        ];
+
+        let markdown = citations_markdown(&results, 1, None);
+        assert!(
+            markdown.contains(
+                "[1] Rust\nhttps://www.rust-lang.org/\nA language empowering everyone.\n\n"
+            )
+        );
+        assert!(markdown.contains("[2] Crates\nhttps://crates.io/\n\n"));
+        assert!(markdown.contains(
+            "```json\n{\"1\":\"https://www.rust-lang.org/\",\"2\":\"https://crates.io/\"}\n```"
+        ));
+        assert!(!markdown.contains("More results"));
+    }
+
+    #[test]
+    fn test_citations_markdown_continues_numbering() {
+        let results = vec![SearchResult::for_test(
+            "Docs",
+            "https://docs.rs/",
+            "Documentation",
+        )];
+
+        let markdown = citations_markdown(&results, 6, Some("abc"));
+        assert!(markdown.contains("[6] Docs\n"));
+        assert!(markdown.contains("{\"6\":\"https://docs.rs/\"}"));
+        assert!(markdown.contains("cursor `abc`"));
+    }
+
+    #[test]
+    fn test_no_results_message() {
+        assert_eq!(
+            no_results_message("qwxzv", &[]),
+            "No results found for \"qwxzv\""
+        );
+        assert_eq!(
+            no_results_message(
+                "rust wasm plugn",
+                &["rust wasm plugin".to_string(), "rust wasi".to_string()]
+            ),
+            "No results found for \"rust wasm plugn\". Try: rust wasm plugin, rust wasi"
+        );
+    }
+
+    #[test]
+    fn test_trim_snippet_prefers_sentence_boundaries() {
+        let text = "Rust is fast. It is also memory safe. Many people enjoy it.";
+        assert_eq!(trim_snippet(text, 100), text);
+        assert_eq!(
+            trim_snippet(text, 45),
+            "Rust is fast. It is also memory safe. …"
+        );
+        // The only sentence end is too early, so cut at a word boundary instead
+        assert_eq!(trim_snippet(text, 30), "Rust is fast. It is also…");
+        assert_eq!(
+            trim_snippet("e.g. version 3.5 and more", 20),
+            "e.g. version 3.5…"
+        );
+        assert_eq!(trim_snippet(text, 0), "");
+    }
+
+    #[test]
+    fn test_truncate_to_bytes() {
+        let mut text = "héllo".to_string();
+        assert!(!truncate_to_bytes(&mut text, 6));
+        assert_eq!(text, "héllo");
+
+        // `é` takes bytes 1..3, so a cut at 2 backs off to 1
+        assert!(truncate_to_bytes(&mut text, 2));
+        assert_eq!(text, "h");
+    }
+
+    #[test]
+    fn test_trim_snippet_respects_utf8() {
+        let text = "Привет мир, это длинный текст 🦀🦀🦀🦀";
+        for max_chars in 0..text.chars().count() {
+            let trimmed = trim_snippet(text, max_chars);
+            assert!(trimmed.chars().count() <= max_chars);
+        }
+        assert_eq!(trim_snippet("🦀🦀🦀🦀", 3), "🦀🦀…");
+    }
+
+    #[test]
+    fn test_fit_to_budget_trims_snippets_first() {
+        let long = "Wikipedia says a lot. ".repeat(40);
+        let mut results = vec![
+            SearchResult::for_test("Rust", "https://www.rust-lang.org/", &long),
+            SearchResult::for_test("Crates", "https://crates.io/", &long),
+        ];
+        let render = |r: &[SearchResult]| serde_json::to_string(r).unwrap();
+        let original = render(&results).chars().count();
+
+        let truncation = fit_to_budget(&mut results, 600, render).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(truncation.dropped_results, 0);
+        assert!(results[0].content.ends_with('…'));
+        let fitted = render(&results).chars().count();
+        assert!(fitted <= 600);
+        assert_eq!(truncation.dropped_chars, original - fitted);
+    }
+
+    #[test]
+    fn test_fit_to_budget_drops_trailing_results() {
+        let mut results: Vec<SearchResult> = (0..20)
+            .map(|i| {
+                SearchResult::for_test(&format!("Result {}", i), "https://example.com/", "Snippet.")
+            })
+            .collect();
+        let render = |r: &[SearchResult]| serde_json::to_string(r).unwrap();
+
+        let truncation = fit_to_budget(&mut results, 400, render).unwrap();
+        assert!(truncation.dropped_results > 0);
+        assert_eq!(results.len(), 20 - truncation.dropped_results);
+        assert_eq!(results[0].title, "Result 0");
+        assert!(render(&results).chars().count() <= 400);
+    }
+
+    #[test]
+    fn test_fit_to_budget_leaves_small_output_alone() {
+        let mut results = vec![SearchResult::for_test(
+            "Docs",
+            "https://docs.rs/",
+            "Documentation",
+        )];
+        assert_eq!(
+            fit_to_budget(&mut results, 10_000, |r| serde_json::to_string(r).unwrap()),
+            None
+        );
+        assert_eq!(results[0].content, "Documentation");
+    }
+
+    #[test]
+    fn test_warnings_note() {
+        assert_eq!(warnings_note(&[], &[]), "");
+        assert_eq!(
+            warnings_note(
+                &["Result page 2 could not be fetched".to_string()],
+                &[
+                    vec!["google".to_string(), "timeout".to_string()],
+                    vec!["bing".to_string()],
+                ],
+            ),
+            "\nSome engines did not respond, results may be incomplete: google (timeout), bing\n\nWarning: Result page 2 could not be fetched\n"
+        );
+    }
+
+    #[test]
+    fn test_related_searches_markdown() {
+        assert_eq!(related_searches_markdown(&[]), None);
+        assert_eq!(
+            related_searches_markdown(&["rust wasi".to_string(), "extism pdk".to_string()])
+                .as_deref(),
+            Some("Related searches\n\n- rust wasi\n- extism pdk\n")
+        );
+    }
+
+    #[test]
+    fn test_results_markdown() {
+        let results = vec![
+            SearchResult::for_test(
+                "Rust",
+                "https://www.rust-lang.org/",
+                "A language empowering everyone.",
+            ),
+            SearchResult::for_test("Crates", "https://crates.io/", ""),
+        ];
+
+        assert_eq!(
+            results_markdown(&results, None),
+            "## [Rust](https://www.rust-lang.org/)\n*general*\n\n\
+             A language empowering everyone.\n\n---\n\n\
+             ## [Crates](https://crates.io/)\n*general*\n\n---\n\n"
+        );
+        assert!(results_markdown(&results, Some("abc")).ends_with("cursor `abc`\n"));
+        assert_eq!(
+            OutputFormat::from_name("Markdown"),
+            Some(OutputFormat::Markdown)
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+/// Share of a query's letters a script needs before the query is taken to be in it
+const SCRIPT_MAJORITY: f64 = 0.5;
+
+/// Frequent function words of major Latin-script languages. Words shared by several of
+/// these languages (`la`, `de`, `en`, `que`...) are left out so a hit is telling.
+const STOP_WORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "of", "is", "for", "with", "how", "what", "why", "does", "to", "from",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "nicht", "mit", "wie", "ein", "eine", "für", "auf",
+            "ich", "was", "warum",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le",
+            "les",
+            "et",
+            "est",
+            "des",
+            "une",
+            "pour",
+            "avec",
+            "comment",
+            "du",
+            "dans",
+            "pourquoi",
+            "qu'est-ce",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "los", "las", "y", "del", "cómo", "qué", "por", "porque", "está", "son", "cuál",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "di", "che", "è", "della", "come", "gli", "perché", "sono", "cosa", "nel",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "não", "uma", "você", "como", "porquê", "são", "isso", "muito", "mais",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "het", "een", "van", "niet", "hoe", "wat", "waarom", "zijn", "voor", "ik", "ook",
+        ],
+    ),
+];
+
+/// Writing systems told apart by the detector
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Script {
+    Latin,
+    Han,
+    Kana,
+    Hangul,
+    Cyrillic,
+    Arabic,
+    Greek,
+    Other,
+}
+
+fn script(c: char) -> Script {
+    match c as u32 {
+        0x0041..=0x024F => Script::Latin,
+        0x0370..=0x03FF | 0x1F00..=0x1FFF => Script::Greek,
+        0x0400..=0x052F => Script::Cyrillic,
+        0x0600..=0x06FF | 0x0750..=0x077F | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+        0x3040..=0x30FF | 0x31F0..=0x31FF | 0xFF66..=0xFF9F => Script::Kana,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF => Script::Han,
+        0x1100..=0x11FF | 0x3130..=0x318F | 0xAC00..=0xD7AF => Script::Hangul,
+        _ => Script::Other,
+    }
+}
+
+/// Guess a query's language as a SearXNG language code, or `None` when unsure.
+///
+/// Non-Latin scripts are recognised from character ranges (CJK, Cyrillic, Arabic, Greek);
+/// Latin-script queries need stop words from a single language to be classified.
+pub fn detect_language(query: &str) -> Option<&'static str> {
+    let mut counts: HashMap<Script, usize> = HashMap::new();
+    for c in query.chars().filter(|c| c.is_alphabetic()) {
+        *counts.entry(script(c)).or_default() += 1;
+    }
+    let letters: usize = counts.values().sum();
+    if letters == 0 {
+        return None;
+    }
+    let share = |script: Script| counts.get(&script).copied().unwrap_or(0) as f64 / letters as f64;
+
+    // Japanese mixes kana with Han characters, so any kana decides it
+    if share(Script::Kana) > 0.0 && share(Script::Kana) + share(Script::Han) > SCRIPT_MAJORITY {
+        return Some("ja");
+    }
+    if share(Script::Han) > SCRIPT_MAJORITY {
+        return Some("zh");
+    }
+    if share(Script::Hangul) > SCRIPT_MAJORITY {
+        return Some("ko");
+    }
+    if share(Script::Cyrillic) > SCRIPT_MAJORITY {
+        let ukrainian = query.chars().any(|c| matches!(c, 'і' | 'ї' | 'є' | 'ґ'));
+        return Some(if ukrainian { "uk" } else { "ru" });
+    }
+    if share(Script::Arabic) > SCRIPT_MAJORITY {
+        return Some("ar");
+    }
+    if share(Script::Greek) > SCRIPT_MAJORITY {
+        return Some("el");
+    }
+    if share(Script::Latin) > SCRIPT_MAJORITY {
+        return detect_latin_language(query);
+    }
+    None
+}
+
+/// Pick the Latin-script language with the most stop-word hits, if one clearly leads
+fn detect_latin_language(query: &str) -> Option<&'static str> {
+    let lowercase = query.to_lowercase();
+    let words: Vec<&str> = lowercase
+        .split(|c: char| !(c.is_alphabetic() || c == '\'' || c == '-'))
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut scores: Vec<(&'static str, usize)> = STOP_WORDS
+        .iter()
+        .map(|(language, stop_words)| {
+            let hits = words.iter().filter(|w| stop_words.contains(w)).count();
+            (*language, hits)
+        })
+        .filter(|(_, hits)| *hits > 0)
+        .collect();
+    scores.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+
+    match scores.as_slice() {
+        [(language, _)] => Some(language),
+        [(language, best), (_, runner_up), ..] if best > runner_up => Some(language),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language_by_script() {
+        assert_eq!(detect_language("异步运行时"), Some("zh"));
+        assert_eq!(detect_language("非同期ランタイムとは"), Some("ja"));
+        assert_eq!(detect_language("비동기 런타임"), Some("ko"));
+        assert_eq!(detect_language("асинхронный рантайм rust"), Some("ru"));
+        assert_eq!(detect_language("її історія"), Some("uk"));
+        assert_eq!(detect_language("وقت التشغيل غير المتزامن"), Some("ar"));
+        assert_eq!(detect_language("ασύγχρονος χρόνος εκτέλεσης"), Some("el"));
+    }
+
+    #[test]
+    fn test_detect_language_by_stop_words() {
+        assert_eq!(
+            detect_language("how does the borrow checker work"),
+            Some("en")
+        );
+        assert_eq!(
+            detect_language("wie funktioniert der Borrow Checker"),
+            Some("de")
+        );
+        assert_eq!(
+            detect_language("comment fonctionne le borrow checker"),
+            Some("fr")
+        );
+        assert_eq!(
+            detect_language("cómo funciona el borrow checker"),
+            Some("es")
+        );
+        assert_eq!(detect_language("waarom is het zo traag"), Some("nl"));
+    }
+
+    #[test]
+    fn test_detect_language_unsure() {
+        assert_eq!(detect_language("rust async runtime"), None);
+        assert_eq!(detect_language("tokio 1.0"), None);
+        assert_eq!(detect_language("12345"), None);
+        assert_eq!(detect_language(""), None);
+    }
+}
@@ -1,58 +1,725 @@
+mod args;
 mod browse;
+mod error;
+mod feed;
+mod format;
+mod language;
 mod pdk;
+mod readability;
 mod searxng;
+mod trace;
 
-use crate::browse::browse;
-use crate::searxng::{SearXNGClient, SearXNGConfig};
+use crate::args::validate_args;
+use crate::browse::{
+    BrowseOptions, FetchOptions, PageFormat, browse, browse_images, browse_links,
+    check_browse_target, fetch_sitemap, max_binary_bytes, media_type, normalize_url,
+};
+use crate::error::{ErrorKind, ToolError};
+use crate::feed::fetch_feed;
+use crate::format::{
+    OutputFormat, Truncation, citations_markdown, fit_to_budget, more_results_line,
+    no_results_message, related_searches_markdown, results_markdown, truncate_to_bytes,
+    warnings_note,
+};
+use crate::searxng::{
+    EngineFilter, MAX_URL_PATTERN_LEN, RESERVED_PARAMS, SearXNGClient, SearXNGConfig, SearchCursor,
+    SearchError, SearchResult, SearchToolResponse, TIME_RANGES, cached_result_url,
+    compile_url_pattern, parse_comma_separated_from_string, redact_url_credentials,
+};
+use crate::trace::{begin_request, info, warn};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use extism_pdk::*;
 use pdk::types::*;
-use serde_json::{Value, json};
+use serde_json::{Map, Value, json};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Default maximum number of URLs accepted by `browse_multiple` and `search_and_browse`
+const DEFAULT_BROWSE_BATCH_MAX: usize = 10;
+
+/// Connection latency above which `health_check` reports SearXNG as degraded
+const DEGRADED_LATENCY_MS: u64 = 2_000;
+
+pub(crate) fn call(mut input: CallToolRequest) -> Result<CallToolResult, Error> {
+    // The host may pass its own ID to correlate logs; it is not a tool argument
+    let supplied_id = input
+        .params
+        .arguments
+        .as_mut()
+        .and_then(|args| args.remove("request_id"));
+    begin_request(supplied_id.as_ref().and_then(Value::as_str));
+
+    // Engine enums are left to SearXNG, so the generic search schema is enough to validate
+    if let Some(tool) = tool_descriptions(search_input_schema(None))
+        .into_iter()
+        .find(|tool| tool.name == input.params.name)
+    {
+        let mut args = input.params.arguments.take().unwrap_or_default();
+        if let Err(e) = validate_args(&tool.input_schema, &mut args) {
+            return Ok(e.into_call_result());
+        }
+        input.params.arguments = Some(args);
+    }
 
-pub(crate) fn call(input: CallToolRequest) -> Result<CallToolResult, Error> {
     match input.params.name.as_str() {
         "search" => search(input),
         "browse" => browse_tool(input),
-        _ => Ok(CallToolResult {
-            is_error: Some(true),
-            content: vec![Content {
-                annotations: None,
-                text: Some(format!("Unknown tool: {}", input.params.name)),
-                mime_type: None,
-                r#type: ContentType::Text,
-                data: None,
-            }],
+        "browse_multiple" => browse_multiple(input),
+        "browse_links" => browse_links_tool(input),
+        "search_and_browse" => search_and_browse(input),
+        "list_engines" => list_engines(input),
+        "answer" => answer_tool(input),
+        "open_result" => open_result(input),
+        "status" => status_tool(input),
+        "health_check" => health_check(input),
+        "fetch_feed" => fetch_feed_tool(input),
+        "fetch_sitemap" => fetch_sitemap_tool(input),
+        _ => Ok(ToolError::new(
+            ErrorKind::UnknownTool,
+            format!("Unknown tool: {}", input.params.name),
+        )
+        .into_call_result()),
+    }
+}
+
+/// Read an optional list argument given either as a comma-separated string or an array of
+/// strings (whose items may themselves be comma-separated), trimming and dropping blanks
+fn list_arg(args: &Map<String, Value>, name: &str) -> Result<Vec<String>, ToolError> {
+    let invalid = || {
+        ToolError::new(
+            ErrorKind::InvalidArgument,
+            format!(
+                "The {} argument must be a comma-separated string or an array of strings",
+                name
+            ),
+        )
+    };
+
+    match args.get(name) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(s)) => Ok(parse_comma_separated_from_string(s)),
+        Some(Value::Array(items)) => items.iter().try_fold(Vec::new(), |mut values, item| {
+            let Value::String(s) = item else {
+                return Err(invalid());
+            };
+            values.extend(parse_comma_separated_from_string(s));
+            Ok(values)
         }),
+        Some(_) => Err(invalid()),
     }
 }
 
+/// `list_arg` joined back into the comma-separated form SearXNG expects, `None` when empty
+fn joined_list_arg(args: &Map<String, Value>, name: &str) -> Result<Option<String>, ToolError> {
+    let values = list_arg(args, name)?;
+    Ok((!values.is_empty()).then(|| values.join(",")))
+}
+
+/// Read the optional `extra_params` object of string values, refusing the names of parameters
+/// the plugin sets itself
+fn extra_params_arg(args: &Map<String, Value>) -> Result<BTreeMap<String, String>, ToolError> {
+    let params = match args.get("extra_params") {
+        None | Some(Value::Null) => return Ok(BTreeMap::new()),
+        Some(Value::Object(params)) => params,
+        Some(_) => {
+            return Err(ToolError::new(
+                ErrorKind::InvalidArgument,
+                "The extra_params argument must be an object of string values",
+            ));
+        }
+    };
+
+    params
+        .iter()
+        .map(|(name, value)| {
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    "extra_params names must not be empty",
+                ));
+            }
+            if RESERVED_PARAMS
+                .iter()
+                .any(|reserved| reserved.eq_ignore_ascii_case(name))
+            {
+                return Err(ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "extra_params cannot set {}: it is set by the search tool itself, use its own argument instead",
+                        name
+                    ),
+                ));
+            }
+            let Value::String(value) = value else {
+                return Err(ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("extra_params value for {} must be a string", name),
+                ));
+            };
+            Ok((name.to_string(), value.clone()))
+        })
+        .collect()
+}
+
 fn search(input: CallToolRequest) -> Result<CallToolResult, Error> {
     let args = input.params.arguments.unwrap_or_default();
     let query = match args.get("query") {
-        Some(Value::String(q)) if !q.is_empty() => q,
-        _ => {
-            return Ok(CallToolResult {
-                is_error: Some(true),
+        Some(Value::String(q)) if !q.is_empty() => Some(q),
+        _ => None,
+    };
+
+    let cursor = match args.get("cursor") {
+        Some(Value::String(c)) if !c.is_empty() => match SearchCursor::decode(c) {
+            Ok(cursor) => Some(cursor),
+            Err(e) => return Ok(ToolError::from(e).into_call_result()),
+        },
+        _ => None,
+    };
+
+    if let (Some(q), Some(c)) = (query, &cursor)
+        && *q != c.query
+    {
+        return Ok(ToolError::new(
+            ErrorKind::InvalidArgument,
+            "The cursor belongs to a different query; omit the query or start a new search",
+        )
+        .into_call_result());
+    }
+
+    let query = match (query, &cursor) {
+        (Some(q), _) => q.clone(),
+        (None, Some(c)) => c.query.clone(),
+        (None, None) => {
+            return Ok(ToolError::new(
+                ErrorKind::InvalidArgument,
+                "Please provide a non-empty query string",
+            )
+            .into_call_result());
+        }
+    };
+
+    let engines = match joined_list_arg(&args, "engines") {
+        Ok(engines) => engines,
+        Err(e) => return Ok(e.into_call_result()),
+    };
+    let categories = match joined_list_arg(&args, "categories") {
+        Ok(categories) => categories,
+        Err(e) => return Ok(e.into_call_result()),
+    };
+    let time_range = match args.get("time_range") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(t)) if TIME_RANGES.contains(&t.as_str()) => Some(t.clone()),
+        Some(other) => {
+            return Ok(ToolError::new(
+                ErrorKind::InvalidArgument,
+                format!(
+                    "Invalid time_range {}: expected one of {}",
+                    other,
+                    TIME_RANGES.join(", ")
+                ),
+            )
+            .into_call_result());
+        }
+    };
+
+    let format = match args.get("format") {
+        None | Some(Value::Null) => Some(OutputFormat::Json),
+        Some(Value::String(f)) => OutputFormat::from_name(f),
+        Some(_) => None,
+    };
+    let Some(format) = format else {
+        return Ok(ToolError::new(
+            ErrorKind::InvalidArgument,
+            format!(
+                "Invalid format {}: expected one of {}",
+                args["format"],
+                OutputFormat::NAMES.join(", ")
+            ),
+        )
+        .into_call_result());
+    };
+
+    let url_pattern = match args.get("url_pattern") {
+        Some(Value::String(p)) if !p.is_empty() => match compile_url_pattern(p) {
+            Ok(_) => Some(p.clone()),
+            Err(e) => return Ok(ToolError::from(e).into_call_result()),
+        },
+        _ => None,
+    };
+
+    let pages = match args.get("pages") {
+        None | Some(Value::Null) => None,
+        Some(value) => match value.as_u64() {
+            Some(n) if n > 0 => Some(n.min(u32::MAX as u64) as u32),
+            _ => {
+                return Ok(ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("Invalid pages {}: expected a positive integer", value),
+                )
+                .into_call_result());
+            }
+        },
+    };
+
+    let extra_params = match extra_params_arg(&args) {
+        Ok(extra_params) => extra_params,
+        Err(e) => return Ok(e.into_call_result()),
+    };
+
+    // A cursor carries the filters of the search that produced it
+    let cursor = cursor.unwrap_or_else(|| SearchCursor {
+        engines,
+        categories,
+        time_range,
+        url_pattern,
+        language: match args.get("language") {
+            Some(Value::String(l)) if !l.trim().is_empty() => Some(l.trim().to_string()),
+            _ => None,
+        },
+        detect_language: match args.get("detect_language") {
+            Some(Value::Bool(b)) => Some(*b),
+            _ => None,
+        },
+        pages,
+        require_content: match args.get("require_content") {
+            Some(Value::Bool(b)) => Some(*b),
+            _ => None,
+        },
+        per_category: matches!(args.get("per_category"), Some(Value::Bool(true))),
+        include_ranking: matches!(args.get("include_ranking"), Some(Value::Bool(true))),
+        include_metadata: matches!(args.get("include_metadata"), Some(Value::Bool(true))),
+        extra_params,
+        ..SearchCursor::start(&query)
+    });
+
+    let max_chars = match args.get("max_chars") {
+        None | Some(Value::Null) => None,
+        Some(value) => match value.as_u64() {
+            Some(n) if n > 0 => Some(n as usize),
+            _ => {
+                return Ok(ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("Invalid max_chars {}: expected a positive integer", value),
+                )
+                .into_call_result());
+            }
+        },
+    };
+
+    let timeout = match args.get("timeout") {
+        None | Some(Value::Null) => None,
+        Some(value) => match value.as_f64().filter(|t| *t > 0.0) {
+            Some(t) => Some(t),
+            None => {
+                return Ok(ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "Invalid timeout {}: expected a positive number of seconds",
+                        value
+                    ),
+                )
+                .into_call_result());
+            }
+        },
+    };
+
+    let mut config = SearXNGConfig::shared();
+    if timeout.is_some() {
+        config.timeout_limit = timeout;
+    }
+    let preflight_check = config.preflight_check;
+    let max_response_chars = config.max_response_chars;
+    let client = SearXNGClient::new(config);
+
+    // Everything is validated and resolved by now; report the request instead of sending it
+    if matches!(args.get("dry_run"), Some(Value::Bool(true))) {
+        return match client.dry_run(&cursor) {
+            Ok(dry_run) => Ok(CallToolResult {
+                is_error: None,
                 content: vec![Content {
                     annotations: None,
-                    text: Some("Please provide a non-empty query string".into()),
-                    mime_type: None,
+                    text: Some(
+                        serde_json::to_string(&dry_run)
+                            .unwrap_or_else(|_| "Serialization error".into()),
+                    ),
+                    mime_type: Some("application/json".into()),
                     r#type: ContentType::Text,
                     data: None,
                 }],
-            });
+            }),
+            Err(e) => Ok(ToolError::from(e).into_call_result()),
+        };
+    }
+
+    if preflight_check {
+        match client.test_connection() {
+            Ok(true) => {}
+            Ok(false) => {
+                return Ok(ToolError::new(
+                    ErrorKind::Connection,
+                    "Unable to connect to SearXNG server",
+                )
+                .into_call_result());
+            }
+            Err(e) => {
+                return Ok(ToolError::from(e)
+                    .context("Connection test failed")
+                    .into_call_result());
+            }
+        }
+    }
+
+    let mut response = match client.simple_search(&cursor) {
+        Ok(response) => response,
+        Err(e) => {
+            return Ok(search_error(e).into_call_result());
         }
     };
+    if response.results.is_empty() {
+        return Ok(no_results(&query, &response.suggestions));
+    }
 
-    let config = SearXNGConfig::default();
-    let client = SearXNGClient::new(config);
-    match client.test_connection() {
-        Ok(true) => match client.simple_search(query) {
-            Ok(response) => Ok(CallToolResult {
+    if let Some(max_chars) = max_chars.or(max_response_chars) {
+        response.trimmed = fit_response(&mut response, &cursor, format, max_chars);
+    }
+
+    let mut content = match format {
+        OutputFormat::Citations => {
+            let mut text = citations_markdown(
+                &response.results,
+                cursor.returned + 1,
+                response.next_cursor.as_deref(),
+            );
+            if let Some(trimmed) = &response.trimmed {
+                text.push_str(&trimmed.note());
+            }
+            text.push_str(&warnings_note(
+                &response.warnings,
+                &response.unresponsive_engines,
+            ));
+            let mut content = vec![Content {
+                annotations: None,
+                text: Some(text),
+                mime_type: Some("text/markdown".into()),
+                r#type: ContentType::Text,
+                data: None,
+            }];
+
+            // Separate block so clients that only show the first one don't bury it in the sources
+            if let Some(related) = related_searches_markdown(&response.suggestions) {
+                content.push(Content {
+                    annotations: None,
+                    text: Some(related),
+                    mime_type: Some("text/markdown".into()),
+                    r#type: ContentType::Text,
+                    data: None,
+                });
+            }
+            content
+        }
+        OutputFormat::Markdown => results_content(
+            results_markdown(&response.results, response.next_cursor.as_deref()),
+            &response,
+        ),
+        OutputFormat::List => results_content(
+            results_list_markdown(
+                &response.results,
+                cursor.returned + 1,
+                response.next_cursor.as_deref(),
+            ),
+            &response,
+        ),
+        OutputFormat::Json => vec![Content {
+            annotations: None,
+            text: Some(
+                serde_json::to_string(&response).unwrap_or_else(|_| "Serialization error".into()),
+            ),
+            mime_type: Some("application/json".into()),
+            r#type: ContentType::Text,
+            data: None,
+        }],
+    };
+
+    // Instant answers such as Wikipedia summaries, kept apart from the ranked results
+    if !response.infoboxes.is_empty() {
+        content.push(Content {
+            annotations: None,
+            text: Some(
+                serde_json::to_string(&json!({ "infoboxes": response.infoboxes }))
+                    .unwrap_or_else(|_| "Serialization error".into()),
+            ),
+            mime_type: Some("application/json".into()),
+            r#type: ContentType::Text,
+            data: None,
+        });
+    }
+
+    Ok(CallToolResult {
+        is_error: None,
+        content,
+    })
+}
+
+/// Rendered results with the response's trimming and warning notes, as one Markdown item
+fn results_content(mut text: String, response: &SearchToolResponse) -> Vec<Content> {
+    if let Some(trimmed) = &response.trimmed {
+        text.push_str(&trimmed.note());
+    }
+    text.push_str(&warnings_note(
+        &response.warnings,
+        &response.unresponsive_engines,
+    ));
+    vec![Content {
+        annotations: None,
+        text: Some(text),
+        mime_type: Some("text/markdown".into()),
+        r#type: ContentType::Text,
+        data: None,
+    }]
+}
+
+/// Render results as a numbered list of `**title**`, `<url>` and `> snippet` entries.
+/// Numbers start at `first_index` so follow-up pages continue the list.
+fn results_list_markdown(
+    results: &[SearchResult],
+    first_index: usize,
+    next_cursor: Option<&str>,
+) -> String {
+    let mut out = String::new();
+    for (i, result) in results.iter().enumerate() {
+        out.push_str(&format!(
+            "{}. **{}**\n   <{}>\n",
+            first_index + i,
+            result.title,
+            result.url
+        ));
+        if !result.content.trim().is_empty() {
+            out.push_str(&format!("   > {}\n", result.content.trim()));
+        }
+        out.push('\n');
+    }
+    if let Some(cursor) = next_cursor {
+        out.push_str(&more_results_line(cursor));
+    }
+    out
+}
+
+/// Shorten the response's results so the rendered output fits in `max_chars` characters
+fn fit_response(
+    response: &mut SearchToolResponse,
+    cursor: &SearchCursor,
+    format: OutputFormat,
+    max_chars: usize,
+) -> Option<Truncation> {
+    let truncation = match format {
+        OutputFormat::Citations => {
+            let next_cursor = response.next_cursor.clone();
+            fit_to_budget(&mut response.results, max_chars, |results| {
+                citations_markdown(results, cursor.returned + 1, next_cursor.as_deref())
+            })
+        }
+        OutputFormat::Markdown => {
+            let next_cursor = response.next_cursor.clone();
+            fit_to_budget(&mut response.results, max_chars, |results| {
+                results_markdown(results, next_cursor.as_deref())
+            })
+        }
+        OutputFormat::List => {
+            let next_cursor = response.next_cursor.clone();
+            fit_to_budget(&mut response.results, max_chars, |results| {
+                results_list_markdown(results, cursor.returned + 1, next_cursor.as_deref())
+            })
+        }
+        OutputFormat::Json => {
+            // Everything but the results array counts against the budget as-is
+            let mut results = std::mem::take(&mut response.results);
+            let overhead = serde_json::to_string(&*response)
+                .map(|s| s.chars().count().saturating_sub(2))
+                .unwrap_or(0);
+            let truncation = fit_to_budget(
+                &mut results,
+                max_chars.saturating_sub(overhead),
+                |results| serde_json::to_string(results).unwrap_or_default(),
+            );
+            response.results = results;
+            truncation
+        }
+    }?;
+
+    // Dropped results come from the current page, so continue right after the kept ones
+    if truncation.dropped_results > 0 {
+        let kept = response.results.len();
+        response.returned_count = kept;
+        response.truncated = true;
+        response.next_cursor = Some(
+            SearchCursor {
+                offset: cursor.offset + kept,
+                returned: cursor.returned + kept,
+                ..cursor.clone()
+            }
+            .encode(),
+        );
+    }
+
+    Some(truncation)
+}
+
+/// Successful, non-error result for a search without hits
+fn no_results(query: &str, suggestions: &[String]) -> CallToolResult {
+    CallToolResult {
+        is_error: None,
+        content: vec![Content {
+            annotations: None,
+            text: Some(no_results_message(query, suggestions)),
+            mime_type: Some("text/plain".into()),
+            r#type: ContentType::Text,
+            data: None,
+        }],
+    }
+}
+
+/// Map a search failure onto a tool error, with a hint at the usual misconfiguration for
+/// failures that self-hosters most often cause themselves
+fn search_error(error: SearchError) -> ToolError {
+    let hint = match &error {
+        SearchError::Connection(_) => Some(
+            "Check that SEARXNG_BASE_URL points at a running SearXNG instance and that the plugin is allowed to reach its host",
+        ),
+        SearchError::Parse(_) => Some(
+            "SearXNG answered, but not with search results JSON: the instance may not have the JSON format enabled (add json to search.formats in its settings.yml), or SEARXNG_BASE_URL may point at a different service",
+        ),
+        _ => None,
+    };
+
+    let mut error = ToolError::from(error).context("Search failed");
+    if let Some(hint) = hint {
+        error.message = format!("{}. {}", error.message, hint);
+    }
+    error
+}
+
+/// Map a browse failure onto a tool error; paywall notices are reported verbatim
+fn browse_error(error: anyhow::Error) -> CallToolResult {
+    let error = ToolError::from(error);
+    if error.kind == ErrorKind::Paywall {
+        error.into_call_result()
+    } else {
+        error.context("Browse failed").into_call_result()
+    }
+}
+
+/// Browse a result of the last search by its 1-based position
+fn open_result(mut input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let args = input.params.arguments.take().unwrap_or_default();
+    let index = match args.get("index").and_then(Value::as_u64) {
+        Some(index) if index > 0 => index as usize,
+        _ => {
+            return Ok(ToolError::new(
+                ErrorKind::InvalidArgument,
+                "Please provide a positive result index",
+            )
+            .into_call_result());
+        }
+    };
+
+    let client = SearXNGClient::new(SearXNGConfig::shared());
+    let results = client.cached_results();
+    let url = match cached_result_url(&results, index) {
+        Ok(url) => url.to_string(),
+        Err(e) => return Ok(ToolError::new(ErrorKind::InvalidArgument, e).into_call_result()),
+    };
+    info!("Opening result {}: {}", index, url);
+
+    input.params.arguments = Some(Map::from_iter([("url".to_string(), json!(url))]));
+    browse_tool(input)
+}
+
+/// Binary body as a base64 image or blob resource block, or a note when it is over the size cap
+fn binary_content(bytes: &[u8], content_type: Option<&str>) -> Content {
+    let limit = max_binary_bytes();
+    if bytes.len() > limit {
+        return Content {
+            annotations: None,
+            text: Some(format!(
+                "Binary content not returned: {} bytes exceeds the BROWSE_MAX_BINARY_BYTES limit of {} bytes",
+                bytes.len(),
+                limit
+            )),
+            mime_type: Some("text/plain".into()),
+            r#type: ContentType::Text,
+            data: None,
+        };
+    }
+
+    let mime = media_type(content_type.unwrap_or("application/octet-stream"));
+    Content {
+        annotations: None,
+        text: None,
+        r#type: if mime.starts_with("image/") {
+            ContentType::Image
+        } else {
+            ContentType::Resource
+        },
+        mime_type: Some(mime),
+        data: Some(STANDARD.encode(bytes)),
+    }
+}
+
+fn browse_tool(input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let args = input.params.arguments.unwrap_or_default();
+    let url = match args.get("url") {
+        Some(Value::String(u)) if !u.is_empty() => u,
+        _ => {
+            return Ok(ToolError::new(
+                ErrorKind::InvalidArgument,
+                "Please provide a non-empty url string",
+            )
+            .into_call_result());
+        }
+    };
+
+    let url = match normalize_url(url).and_then(|url| check_browse_target(&url).map(|_| url)) {
+        Ok(url) => url,
+        Err(e) => return Ok(ToolError::new(ErrorKind::InvalidArgument, e).into_call_result()),
+    };
+
+    let fetch_options = FetchOptions {
+        user_agent: match args.get("user_agent") {
+            Some(Value::String(ua)) if !ua.trim().is_empty() => Some(ua.trim().to_string()),
+            _ => None,
+        },
+        follow_redirects: match args.get("follow_redirects") {
+            Some(Value::Bool(b)) => Some(*b),
+            _ => None,
+        },
+        max_redirects: match args.get("max_redirects") {
+            None | Some(Value::Null) => None,
+            Some(value) => match value.as_u64() {
+                Some(n) => Some(n as usize),
+                None => {
+                    return Ok(ToolError::new(
+                        ErrorKind::InvalidArgument,
+                        format!(
+                            "Invalid max_redirects {}: expected a non-negative integer",
+                            value
+                        ),
+                    )
+                    .into_call_result());
+                }
+            },
+        },
+    };
+
+    let extract_images = matches!(args.get("extract_images"), Some(Value::Bool(true)));
+    if extract_images {
+        return match browse_images(&url, &fetch_options) {
+            Ok(images) => Ok(CallToolResult {
                 is_error: None,
                 content: vec![Content {
                     annotations: None,
                     text: Some(
-                        serde_json::to_string(&response)
+                        serde_json::to_string(&images)
                             .unwrap_or_else(|_| "Serialization error".into()),
                     ),
                     mime_type: Some("application/json".into()),
@@ -60,87 +727,698 @@ fn search(input: CallToolRequest) -> Result<CallToolResult, Error> {
                     data: None,
                 }],
             }),
-            Err(e) => Ok(CallToolResult {
-                is_error: Some(true),
-                content: vec![Content {
+            Err(e) => Ok(browse_error(e)),
+        };
+    }
+
+    let format = match args.get("format") {
+        None | Some(Value::Null) => Some(PageFormat::Markdown),
+        Some(Value::String(f)) => PageFormat::from_name(f),
+        Some(_) => None,
+    };
+    let Some(format) = format else {
+        return Ok(ToolError::new(
+            ErrorKind::InvalidArgument,
+            format!(
+                "Invalid format {}: expected one of {}",
+                args["format"],
+                PageFormat::NAMES.join(", ")
+            ),
+        )
+        .into_call_result());
+    };
+
+    // Not split on commas like list_arg: a selector group such as `h1, h2` stays one selector
+    let selectors = match args.get("selector") {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let options = BrowseOptions {
+        selectors: selectors
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        readability: match args.get("readability") {
+            Some(Value::Bool(b)) => Some(*b),
+            _ => None,
+        },
+        generate_toc: matches!(args.get("generate_toc"), Some(Value::Bool(true))),
+        format,
+        fetch: fetch_options,
+    };
+    let include_metadata = !matches!(args.get("include_metadata"), Some(Value::Bool(false)));
+    let return_binary = matches!(args.get("return_binary"), Some(Value::Bool(true)));
+
+    match browse(&url, &options) {
+        Ok(output) => {
+            let mut content = vec![Content {
+                annotations: None,
+                text: Some(output.markdown),
+                mime_type: Some(output.mime_type.into()),
+                r#type: ContentType::Text,
+                data: None,
+            }];
+
+            if let Some(bytes) = output.binary.filter(|_| return_binary) {
+                content.push(binary_content(
+                    &bytes,
+                    output.metadata.content_type.as_deref(),
+                ));
+            }
+
+            if include_metadata {
+                content.push(Content {
                     annotations: None,
-                    text: Some(format!("Search failed: {}", e)),
-                    mime_type: None,
+                    text: Some(
+                        serde_json::to_string(&output.metadata)
+                            .unwrap_or_else(|_| "Serialization error".into()),
+                    ),
+                    mime_type: Some("application/json".into()),
                     r#type: ContentType::Text,
                     data: None,
-                }],
+                });
+            }
+
+            if !output.open_graph.is_empty() {
+                content.push(Content {
+                    annotations: None,
+                    text: Some(
+                        serde_json::to_string(&output.open_graph)
+                            .unwrap_or_else(|_| "Serialization error".into()),
+                    ),
+                    mime_type: Some("application/json".into()),
+                    r#type: ContentType::Text,
+                    data: None,
+                });
+            }
+
+            if !output.json_ld.is_empty() {
+                content.push(Content {
+                    annotations: None,
+                    text: Some(
+                        serde_json::to_string(&output.json_ld)
+                            .unwrap_or_else(|_| "Serialization error".into()),
+                    ),
+                    mime_type: Some("application/json".into()),
+                    r#type: ContentType::Text,
+                    data: None,
+                });
+            }
+
+            Ok(CallToolResult {
+                is_error: None,
+                content,
+            })
+        }
+        Err(e) => Ok(browse_error(e)),
+    }
+}
+
+fn browse_multiple(input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let args = input.params.arguments.unwrap_or_default();
+    let urls = match args.get("urls") {
+        Some(Value::Array(items)) if !items.is_empty() => items
+            .iter()
+            .map(|item| match item {
+                Value::String(u) if !u.is_empty() => Some(u.clone()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>(),
+        _ => None,
+    };
+    let Some(urls) = urls else {
+        return Ok(ToolError::new(
+            ErrorKind::InvalidArgument,
+            "Please provide urls as a non-empty array of URL strings",
+        )
+        .into_call_result());
+    };
+
+    let batch_max = browse_batch_max();
+    if urls.len() > batch_max {
+        return Ok(ToolError::new(
+            ErrorKind::InvalidArgument,
+            format!(
+                "Too many urls: {} given, at most {} allowed",
+                urls.len(),
+                batch_max
+            ),
+        )
+        .into_call_result());
+    }
+
+    // One failing page should not hide the others, so errors are reported per URL
+    let options = BrowseOptions::default();
+    let pages: Vec<Value> = urls
+        .into_iter()
+        .map(|url| match browse(&url, &options) {
+            Ok(output) => json!({ "url": url, "content": output.markdown, "error": null }),
+            Err(e) => json!({
+                "url": url,
+                "content": null,
+                "error": ToolError::from(e).message,
             }),
+        })
+        .collect();
+
+    Ok(CallToolResult {
+        is_error: None,
+        content: vec![Content {
+            annotations: None,
+            text: Some(Value::Array(pages).to_string()),
+            mime_type: Some("application/json".into()),
+            r#type: ContentType::Text,
+            data: None,
+        }],
+    })
+}
+
+/// Maximum number of pages fetched in one call, from `BROWSE_BATCH_MAX`
+fn browse_batch_max() -> usize {
+    config::get("BROWSE_BATCH_MAX")
+        .ok()
+        .flatten()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_BROWSE_BATCH_MAX)
+}
+
+fn search_and_browse(input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let args = input.params.arguments.unwrap_or_default();
+    let query = match args.get("query") {
+        Some(Value::String(q)) if !q.is_empty() => q,
+        _ => {
+            return Ok(ToolError::new(
+                ErrorKind::InvalidArgument,
+                "Please provide a non-empty query string",
+            )
+            .into_call_result());
+        }
+    };
+
+    let batch_max = browse_batch_max();
+    let count = match args.get("count") {
+        None | Some(Value::Null) => 1,
+        Some(value) => match value.as_u64() {
+            Some(n) if n > 0 && n as usize <= batch_max => n as usize,
+            _ => {
+                return Ok(ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "Invalid count {}: expected an integer from 1 to {}",
+                        value, batch_max
+                    ),
+                )
+                .into_call_result());
+            }
         },
-        Ok(false) => Ok(CallToolResult {
-            is_error: Some(true),
+    };
+
+    let num_results = match positive_int_arg(&args, "num_results") {
+        Ok(num_results) => num_results,
+        Err(e) => return Ok(e.into_call_result()),
+    };
+    let max_content_bytes = match positive_int_arg(&args, "max_content_bytes") {
+        Ok(max_content_bytes) => max_content_bytes,
+        Err(e) => return Ok(e.into_call_result()),
+    };
+
+    // Applied as the page limit so next_cursor resumes right after the listed results
+    let cursor = SearchCursor {
+        limit: num_results,
+        ..SearchCursor::start(query)
+    };
+    let client = SearXNGClient::new(SearXNGConfig::shared());
+    let response = match client.simple_search(&cursor) {
+        Ok(response) => response,
+        Err(e) => {
+            return Ok(search_error(e).into_call_result());
+        }
+    };
+    if response.results.is_empty() {
+        return Ok(no_results(query, &response.suggestions));
+    }
+
+    // As in browse_multiple, a page that fails to load is reported in its own entry
+    let options = BrowseOptions::default();
+    let pages: Vec<Value> = response
+        .results
+        .iter()
+        .take(count)
+        .map(|result| match browse(&result.url, &options) {
+            Ok(mut output) => {
+                let truncated = max_content_bytes
+                    .is_some_and(|max| truncate_to_bytes(&mut output.markdown, max));
+                let mut page = json!({
+                    "title": result.title,
+                    "url": result.url,
+                    "markdown": output.markdown,
+                });
+                if truncated {
+                    page["truncated"] = json!(true);
+                }
+                page
+            }
+            Err(e) => json!({
+                "title": result.title,
+                "url": result.url,
+                "markdown": null,
+                "error": ToolError::from(e).message,
+            }),
+        })
+        .collect();
+
+    // Pages stay the first item so clients reading only that one keep working
+    Ok(CallToolResult {
+        is_error: None,
+        content: vec![
+            Content {
+                annotations: None,
+                text: Some(Value::Array(pages).to_string()),
+                mime_type: Some("application/json".into()),
+                r#type: ContentType::Text,
+                data: None,
+            },
+            Content {
+                annotations: None,
+                text: Some(
+                    serde_json::to_string(&response)
+                        .unwrap_or_else(|_| "Serialization error".into()),
+                ),
+                mime_type: Some("application/json".into()),
+                r#type: ContentType::Text,
+                data: None,
+            },
+        ],
+    })
+}
+
+/// Read an optional positive integer argument
+fn positive_int_arg(args: &Map<String, Value>, name: &str) -> Result<Option<usize>, ToolError> {
+    match args.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => match value.as_u64() {
+            Some(n) if n > 0 => Ok(Some(n as usize)),
+            _ => Err(ToolError::new(
+                ErrorKind::InvalidArgument,
+                format!("Invalid {} {}: expected a positive integer", name, value),
+            )),
+        },
+    }
+}
+
+fn browse_links_tool(input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let args = input.params.arguments.unwrap_or_default();
+    let url = match args.get("url") {
+        Some(Value::String(u)) if !u.is_empty() => u,
+        _ => {
+            return Ok(ToolError::new(
+                ErrorKind::InvalidArgument,
+                "Please provide a non-empty url string",
+            )
+            .into_call_result());
+        }
+    };
+
+    match browse_links(url) {
+        Ok(links) => Ok(CallToolResult {
+            is_error: None,
             content: vec![Content {
                 annotations: None,
-                text: Some("Unable to connect to SearXNG server".into()),
-                mime_type: None,
+                text: Some(
+                    serde_json::to_string(&links).unwrap_or_else(|_| "Serialization error".into()),
+                ),
+                mime_type: Some("application/json".into()),
                 r#type: ContentType::Text,
                 data: None,
             }],
         }),
-        Err(e) => Ok(CallToolResult {
-            is_error: Some(true),
+        Err(e) => Ok(browse_error(e)),
+    }
+}
+
+fn fetch_feed_tool(input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let args = input.params.arguments.unwrap_or_default();
+    let url = match args.get("url") {
+        Some(Value::String(u)) if !u.is_empty() => u,
+        _ => {
+            return Ok(ToolError::new(
+                ErrorKind::InvalidArgument,
+                "Please provide a non-empty url string",
+            )
+            .into_call_result());
+        }
+    };
+
+    match fetch_feed(url) {
+        Ok(entries) => Ok(CallToolResult {
+            is_error: None,
             content: vec![Content {
                 annotations: None,
-                text: Some(format!("Connection test failed: {}", e)),
-                mime_type: None,
+                text: Some(
+                    serde_json::to_string(&entries)
+                        .unwrap_or_else(|_| "Serialization error".into()),
+                ),
+                mime_type: Some("application/json".into()),
                 r#type: ContentType::Text,
                 data: None,
             }],
         }),
+        Err(e) => Ok(ToolError::from(e)
+            .context("Fetching feed failed")
+            .into_call_result()),
     }
 }
 
-fn browse_tool(input: CallToolRequest) -> Result<CallToolResult, Error> {
+fn fetch_sitemap_tool(input: CallToolRequest) -> Result<CallToolResult, Error> {
     let args = input.params.arguments.unwrap_or_default();
     let url = match args.get("url") {
         Some(Value::String(u)) if !u.is_empty() => u,
         _ => {
-            return Ok(CallToolResult {
-                is_error: Some(true),
-                content: vec![Content {
-                    annotations: None,
-                    text: Some("Please provide a non-empty url string".into()),
-                    mime_type: None,
-                    r#type: ContentType::Text,
-                    data: None,
-                }],
-            });
+            return Ok(ToolError::new(
+                ErrorKind::InvalidArgument,
+                "Please provide a non-empty url string",
+            )
+            .into_call_result());
         }
     };
 
-    match browse(url) {
-        Ok(html) => Ok(CallToolResult {
+    match fetch_sitemap(url) {
+        Ok(urls) => Ok(CallToolResult {
             is_error: None,
             content: vec![Content {
                 annotations: None,
-                text: Some(html),
-                mime_type: Some("text/markdown".into()),
+                text: Some(
+                    serde_json::to_string(&urls).unwrap_or_else(|_| "Serialization error".into()),
+                ),
+                mime_type: Some("application/json".into()),
                 r#type: ContentType::Text,
                 data: None,
             }],
         }),
-        Err(e) => Ok(CallToolResult {
-            is_error: Some(true),
+        Err(e) => Ok(ToolError::from(e)
+            .context("Fetching sitemap failed")
+            .into_call_result()),
+    }
+}
+
+fn list_engines(input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let args = input.params.arguments.unwrap_or_default();
+    let filter = match args.get("filter") {
+        None => EngineFilter::Enabled,
+        Some(Value::String(f)) => match f.to_lowercase().as_str() {
+            "enabled" => EngineFilter::Enabled,
+            "disabled" => EngineFilter::Disabled,
+            "all" => EngineFilter::All,
+            _ => {
+                return Ok(ToolError::new(
+                    ErrorKind::InvalidArgument,
+                    format!(
+                        "Invalid filter '{}': expected one of enabled, disabled, all",
+                        f
+                    ),
+                )
+                .into_call_result());
+            }
+        },
+        Some(_) => {
+            return Ok(ToolError::new(
+                ErrorKind::InvalidArgument,
+                "The filter argument must be a string",
+            )
+            .into_call_result());
+        }
+    };
+
+    let config = SearXNGConfig::shared();
+    let client = SearXNGClient::new(config);
+    match client.get_engine_categories(filter) {
+        Ok(engines) => {
+            let mut names: Vec<&String> = engines.keys().collect();
+            names.sort();
+            let engine_list: Vec<Value> = names
+                .into_iter()
+                .map(|name| json!({ "name": name, "categories": engines[name] }))
+                .collect();
+
+            Ok(CallToolResult {
+                is_error: None,
+                content: vec![Content {
+                    annotations: None,
+                    text: Some(
+                        serde_json::to_string(&engine_list)
+                            .unwrap_or_else(|_| "Serialization error".into()),
+                    ),
+                    mime_type: Some("application/json".into()),
+                    r#type: ContentType::Text,
+                    data: None,
+                }],
+            })
+        }
+        Err(e) => Ok(ToolError::from(e)
+            .context("Failed to list engines")
+            .into_call_result()),
+    }
+}
+
+fn answer_tool(input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let args = input.params.arguments.unwrap_or_default();
+    let query = match args.get("query") {
+        Some(Value::String(q)) if !q.trim().is_empty() => q.trim(),
+        _ => {
+            return Ok(
+                ToolError::new(ErrorKind::InvalidArgument, "Please provide a query")
+                    .into_call_result(),
+            );
+        }
+    };
+
+    let client = SearXNGClient::new(SearXNGConfig::shared());
+    match client.answer(query) {
+        Ok(answers) if answers.is_empty() => Ok(ToolError::new(
+            ErrorKind::UpstreamEngine,
+            format!(
+                "SearXNG has no direct answer for '{}'; use the search tool instead",
+                query
+            ),
+        )
+        .into_call_result()),
+        Ok(answers) => Ok(CallToolResult {
+            is_error: None,
             content: vec![Content {
                 annotations: None,
-                text: Some(format!("Browse failed: {}", e)),
-                mime_type: None,
+                text: Some(json!({ "query": query, "answers": answers }).to_string()),
+                mime_type: Some("application/json".into()),
                 r#type: ContentType::Text,
                 data: None,
             }],
         }),
+        Err(e) => Ok(ToolError::from(e)
+            .context("Answer failed")
+            .into_call_result()),
+    }
+}
+
+/// Report whether SearXNG is reachable; an unreachable server is a result, not an error
+fn status_tool(_input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let config = SearXNGConfig::shared();
+    let base_url = redact_url_credentials(&config.base_url);
+    let client = SearXNGClient::new(config);
+
+    let mut status = json!({
+        "reachable": false,
+        "base_url": base_url,
+        "engine_count": null,
+        "version": env!("CARGO_PKG_VERSION"),
+    });
+    match client.test_connection() {
+        Ok(true) => {
+            status["reachable"] = json!(true);
+            match client.get_engines(EngineFilter::All) {
+                Ok(engines) => status["engine_count"] = json!(engines.len()),
+                Err(e) => warn!("Status check could not count engines: {}", e),
+            }
+        }
+        Ok(false) => status["error"] = json!("SearXNG answered with an error status"),
+        Err(e) => status["error"] = json!(e.to_string()),
+    }
+
+    Ok(CallToolResult {
+        is_error: None,
+        content: vec![Content {
+            annotations: None,
+            text: Some(status.to_string()),
+            mime_type: Some("application/json".into()),
+            r#type: ContentType::Text,
+            data: None,
+        }],
+    })
+}
+
+/// Health of the SearXNG connection: `ok`, `degraded` when it answered slowly or with an
+/// error status, or `error` when it could not be reached
+fn health_status(connection: &Result<bool, SearchError>, latency_ms: u64) -> &'static str {
+    match connection {
+        Ok(true) if latency_ms <= DEGRADED_LATENCY_MS => "ok",
+        Ok(_) => "degraded",
+        Err(_) => "error",
+    }
+}
+
+/// Lightweight liveness probe: one connection test, timed
+fn health_check(_input: CallToolRequest) -> Result<CallToolResult, Error> {
+    let client = SearXNGClient::new(SearXNGConfig::shared());
+
+    let started = std::time::Instant::now();
+    let connection = client.test_connection();
+    let latency_ms = started.elapsed().as_millis() as u64;
+    if let Err(e) = &connection {
+        warn!("Health check failed: {}", e);
     }
+
+    let health = json!({
+        "status": health_status(&connection, latency_ms),
+        "searxng_reachable": matches!(connection, Ok(true)),
+        "latency_ms": latency_ms,
+        "plugin_version": env!("CARGO_PKG_VERSION"),
+    });
+
+    Ok(CallToolResult {
+        is_error: None,
+        content: vec![Content {
+            annotations: None,
+            text: Some(health.to_string()),
+            mime_type: Some("application/json".into()),
+            r#type: ContentType::Text,
+            data: None,
+        }],
+    })
+}
+
+/// Build the search tool's input schema, constraining engines and categories to the
+/// instance's enabled engines when they could be fetched
+fn search_input_schema(engines: Option<&HashMap<String, Vec<String>>>) -> Map<String, Value> {
+    let mut engines_items = json!({ "type": "string" });
+    let mut categories_items = json!({ "type": "string" });
+
+    if let Some(engines) = engines.filter(|e| !e.is_empty()) {
+        let names: BTreeSet<&str> = engines.keys().map(|s| s.as_str()).collect();
+        let categories: BTreeSet<&str> = engines.values().flatten().map(|s| s.as_str()).collect();
+
+        engines_items["enum"] = json!(names);
+        if !categories.is_empty() {
+            categories_items["enum"] = json!(categories);
+        }
+    }
+
+    json!({
+        "type": "object",
+        "properties": {
+            "query": {
+                "type": "string",
+                "description": "The search query",
+            },
+            "engines": {
+                "type": "array",
+                "items": engines_items,
+                "description": "Engines to query instead of the configured defaults (an array, or a comma-separated string)",
+            },
+            "categories": {
+                "type": "array",
+                "items": categories_items,
+                "description": "Categories to search instead of the configured defaults (an array, or a comma-separated string)",
+            },
+            "url_pattern": {
+                "type": "string",
+                "maxLength": MAX_URL_PATTERN_LEN,
+                "description": "Only return results whose URL matches this regular expression (case-sensitive, matched anywhere in the full URL), e.g. \"/docs/\" or \"\\.pdf$\"",
+            },
+            "pages": {
+                "type": "integer",
+                "minimum": 1,
+                "description": "Fetch this many SearXNG result pages and merge them, deduplicated by URL, before ranking (at most SEARXNG_MAX_PAGES). Warnings list any page that failed",
+            },
+            "require_content": {
+                "type": "boolean",
+                "description": "Drop results that have no snippet (defaults to SEARXNG_REQUIRE_CONTENT). The response reports how many were dropped in empty_content_dropped",
+            },
+            "per_category": {
+                "type": "boolean",
+                "description": "Search each category separately and interleave the results round-robin (deduplicated by URL), instead of one combined search. Each result keeps its category",
+            },
+            "include_metadata": {
+                "type": "boolean",
+                "description": "Add engine_counts to the JSON response: how many of the page's results (after filters, before pagination) each engine found",
+            },
+            "include_ranking": {
+                "type": "boolean",
+                "description": "Add a ranking object to each JSON result: positions (its position in each engine's results), rank (1-based position in the returned results, continuing across cursor pages) and score",
+            },
+            "language": {
+                "type": "string",
+                "description": "Language code to search in (e.g. \"de\" or \"pt-BR\"), instead of the configured or detected language",
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate the arguments and return the SearXNG URL that would be requested ({url, requests, detected_language}) without searching",
+            },
+            "extra_params": {
+                "type": "object",
+                "additionalProperties": { "type": "string" },
+                "description": "Escape hatch for SearXNG query parameters this tool does not model (e.g. {\"theme\": \"simple\"} or an instance's custom ones), appended to the request as given and unchecked. Parameters the tool sets itself (q, format, categories, engines, language, pageno, time_range, safesearch, timeout_limit, enabled_plugins, disabled_plugins) are rejected",
+            },
+            "detect_language": {
+                "type": "boolean",
+                "description": "Guess the language from the query (defaults to SEARXNG_AUTO_LANGUAGE). The guess is reported as detected_language",
+            },
+            "time_range": {
+                "type": "string",
+                "enum": TIME_RANGES,
+                "description": "Only return results from this time range (defaults to SEARXNG_DEFAULT_TIME_RANGE)",
+            },
+            "format": {
+                "type": "string",
+                "enum": OutputFormat::NAMES,
+                "description": "Output format: json (default), citations for numbered [n] sources to cite (followed by a separate block of related searches), markdown for readable result headings, or list for a numbered list of bold titles, links and quoted snippets",
+            },
+            "timeout": {
+                "type": "number",
+                "exclusiveMinimum": 0,
+                "description": "Seconds SearXNG may wait for its engines (fractions allowed). With a tight limit results may be partial; engines that ran out of time are listed in unresponsive_engines",
+            },
+            "max_chars": {
+                "type": "integer",
+                "minimum": 1,
+                "description": "Maximum length of the response in characters; snippets are shortened and trailing results dropped to fit",
+            },
+            "cursor": {
+                "type": "string",
+                "description": "The next_cursor value from a previous search, to fetch its next results (keeps that search's filters)",
+            },
+        },
+    })
+    .as_object()
+    .unwrap()
+    .clone()
 }
 
 pub(crate) fn describe() -> Result<ListToolsResult, Error> {
-    // Log available engines on plugin load
-    let config = SearXNGConfig::default();
+    // Log available engines on plugin load; they also constrain the search schema
+    let config = SearXNGConfig::shared();
+    if !config.describe_probe {
+        info!("Skipping the SearXNG engine probe (SEARXNG_DESCRIBE_PROBE=false)");
+        return Ok(ListToolsResult {
+            tools: tool_descriptions(search_input_schema(None)),
+        });
+    }
     let client = SearXNGClient::new(config);
-    match client.get_engines(crate::searxng::EngineFilter::Enabled) {
+    let engines = match client.get_engine_categories(EngineFilter::Enabled) {
         Ok(engines) => {
             let engine_list = engines
                 .keys()
@@ -148,48 +1426,441 @@ pub(crate) fn describe() -> Result<ListToolsResult, Error> {
                 .collect::<Vec<_>>()
                 .join(", ");
             info!("Available SearXNG engines: {}", engine_list);
+            Some(engines)
         }
         Err(e) => {
             warn!("Failed to fetch SearXNG engines: {}", e);
+            None
         }
-    }
+    };
 
     Ok(ListToolsResult {
-        tools: vec![
-            ToolDescription {
-                name: "search".into(),
-                description: "Perform web search using SearXNG".into(),
-                input_schema: json!({
-                    "type": "object",
-                    "properties": {
-                        "query": {
-                            "type": "string",
-                            "description": "The search query",
-                        },
-                    },
-                    "required": ["query"],
-                })
-                .as_object()
-                .unwrap()
-                .clone(),
-            },
-            ToolDescription {
-                name: "browse".into(),
-                description: "Fetch content from a URL as Markdown".into(),
-                input_schema: json!({
-                    "type": "object",
-                    "properties": {
-                        "url": {
-                            "type": "string",
-                            "description": "The URL to browse",
-                        },
-                    },
-                    "required": ["url"],
-                })
-                .as_object()
-                .unwrap()
-                .clone(),
-            },
-        ],
+        tools: tool_descriptions(search_input_schema(engines.as_ref())),
     })
 }
+
+/// Every tool offered by the plugin, with the given schema for the search tool
+fn tool_descriptions(search_schema: Map<String, Value>) -> Vec<ToolDescription> {
+    vec![
+        ToolDescription {
+            name: "search".into(),
+            description: "Perform web search using SearXNG".into(),
+            input_schema: search_schema,
+        },
+        ToolDescription {
+            name: "browse".into(),
+            description: "Fetch content from a URL as Markdown (or, with format, as cleaned HTML or plain text), following redirects unless follow_redirects is false".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "The URL to browse",
+                    },
+                    "extract_images": {
+                        "type": "boolean",
+                        "description": "Return a JSON list of the page's images (src, alt, width, height) instead of Markdown",
+                    },
+                    "selector": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "CSS selector, or an array of them, e.g. \"#changelog\" or [\"h1\", \"table.results\"]. Only the matching elements are converted, the matches of each selector in turn. When nothing matches, a note with the page title is returned instead of an error",
+                    },
+                    "readability": {
+                        "type": "boolean",
+                        "description": "Extract only the main article content, dropping navigation, sidebars and footers",
+                    },
+                    "generate_toc": {
+                        "type": "boolean",
+                        "description": "Prepend a table of contents linking to the page's headings (markdown format only)",
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": PageFormat::NAMES,
+                        "description": "How HTML pages are returned: markdown (default), html (the page's HTML with styles, scripts and comments stripped, as text/html) or text (plain text with paragraph breaks). JSON, feeds and plain text keep their own rendering",
+                    },
+                    "follow_redirects": {
+                        "type": "boolean",
+                        "description": "Follow HTTP redirects (defaults to BROWSE_FOLLOW_REDIRECTS, on unless configured off). When off, a redirect fails with its status and target URL",
+                    },
+                    "max_redirects": {
+                        "type": "integer",
+                        "minimum": 0,
+                        "description": "Most redirects to follow (defaults to BROWSE_MAX_REDIRECTS)",
+                    },
+                    "include_metadata": {
+                        "type": "boolean",
+                        "description": "Add a JSON block describing the final response: final_url, redirects, status, content_type, size_bytes, title, word_count and reading_time_seconds (default true)",
+                    },
+                    "user_agent": {
+                        "type": "string",
+                        "description": "User-Agent header to send instead of BROWSE_USER_AGENT, e.g. a browser's for sites that refuse unknown clients",
+                    },
+                    "return_binary": {
+                        "type": "boolean",
+                        "description": "For binary content (PDFs, images, archives), also return the file as base64 data: an image block for images, a resource block otherwise. Only files up to BROWSE_MAX_BINARY_BYTES are returned (default false)",
+                    },
+                },
+                "required": ["url"],
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "browse_multiple".into(),
+            description: "Fetch several URLs in one call and return a JSON array of {url, content, error}, with each page's content as Markdown".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "urls": {
+                        "type": "array",
+                        "items": { "type": "string" },
+                        "description": "The URLs to browse, in order",
+                    },
+                },
+                "required": ["urls"],
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "search_and_browse".into(),
+            description: "Search, then fetch the top results. Returns a JSON array of {title, url, markdown} (a page that fails to load gets an error field instead of markdown), followed by the search response as a second JSON item".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "The search query",
+                    },
+                    "count": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "How many of the top results to fetch (default 1, at most BROWSE_BATCH_MAX)",
+                    },
+                    "num_results": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Most search results to list in the search response and on later pages; can lower SEARXNG_NUM_RESULTS but not raise it (defaults to SEARXNG_NUM_RESULTS)",
+                    },
+                    "max_content_bytes": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Cut each page's markdown to this many bytes; cut pages are marked truncated: true",
+                    },
+                },
+                "required": ["query"],
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "browse_links".into(),
+            description: "List the links on a web page as a JSON array of {url, text}, with absolute, deduplicated URLs".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "The URL of the page",
+                    },
+                },
+                "required": ["url"],
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "fetch_feed".into(),
+            description: "Fetch an RSS or Atom feed and return its entries as JSON. HTML pages are searched for an advertised feed".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL of the feed, or of a page that links to one",
+                    },
+                },
+                "required": ["url"],
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "fetch_sitemap".into(),
+            description: "List the pages of a website from its sitemap.xml as JSON, following sitemap indexes".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "Site root (sitemap.xml is appended) or the full URL of a sitemap",
+                    },
+                },
+                "required": ["url"],
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "list_engines".into(),
+            description: "List the search engines available on the SearXNG instance, with their categories".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "filter": {
+                        "type": "string",
+                        "enum": ["enabled", "disabled", "all"],
+                        "description": "Which engines to list (default: enabled)",
+                    },
+                },
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "status".into(),
+            description: "Check the plugin's connection to SearXNG. Returns {reachable, base_url, engine_count, version}; an unreachable server is reported as reachable: false with an error, not as a failure".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "health_check".into(),
+            description: "Liveness probe: test the SearXNG connection without searching. Returns {status: ok|degraded|error, searxng_reachable, latency_ms, plugin_version}".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "open_result".into(),
+            description: "Browse a result of the last search by its position (1 = first result returned), as Markdown like the browse tool".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "index": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "1-based position of the result in the last search response",
+                    },
+                },
+                "required": ["index"],
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+        ToolDescription {
+            name: "answer".into(),
+            description: "Get a direct answer from SearXNG's answerers for conversions and calculations such as \"10 usd in eur\" or \"sqrt(2)\", without web results. Much cheaper than a search; fails when there is no direct answer".into(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {
+                        "type": "string",
+                        "description": "The expression or question to answer",
+                    },
+                },
+                "required": ["query"],
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_input_schema_uses_instance_engines() {
+        let engines = HashMap::from([
+            ("wikipedia".to_string(), vec!["general".to_string()]),
+            (
+                "arxiv".to_string(),
+                vec!["science".to_string(), "general".to_string()],
+            ),
+        ]);
+
+        let schema = search_input_schema(Some(&engines));
+        let properties = &schema["properties"];
+        assert_eq!(
+            properties["engines"]["items"]["enum"],
+            json!(["arxiv", "wikipedia"])
+        );
+        assert_eq!(
+            properties["categories"]["items"]["enum"],
+            json!(["general", "science"])
+        );
+        assert_eq!(
+            properties["time_range"]["enum"],
+            json!(["day", "week", "month", "year"])
+        );
+    }
+
+    #[test]
+    fn test_search_input_schema_falls_back_to_strings() {
+        let schema = search_input_schema(None);
+        let properties = &schema["properties"];
+        assert_eq!(properties["engines"]["items"], json!({ "type": "string" }));
+        assert_eq!(
+            properties["categories"]["items"],
+            json!({ "type": "string" })
+        );
+    }
+
+    #[test]
+    fn test_health_status() {
+        assert_eq!(health_status(&Ok(true), 120), "ok");
+        assert_eq!(
+            health_status(&Ok(true), DEGRADED_LATENCY_MS + 1),
+            "degraded"
+        );
+        assert_eq!(health_status(&Ok(false), 120), "degraded");
+        assert_eq!(
+            health_status(&Err(SearchError::Connection("refused".into())), 5),
+            "error"
+        );
+    }
+
+    #[test]
+    fn test_results_list_markdown() {
+        let results = vec![
+            SearchResult::for_test(
+                "Rust",
+                "https://www.rust-lang.org/",
+                " A language empowering everyone. ",
+            ),
+            SearchResult::for_test("Crates", "https://crates.io/", ""),
+        ];
+
+        assert_eq!(
+            results_list_markdown(&results, 1, None),
+            "1. **Rust**\n   <https://www.rust-lang.org/>\n   > A language empowering everyone.\n\n\
+             2. **Crates**\n   <https://crates.io/>\n\n"
+        );
+        let next_page = results_list_markdown(&results[1..], 6, Some("abc"));
+        assert!(next_page.starts_with("6. **Crates**"));
+        assert!(next_page.ends_with("cursor `abc`\n"));
+        assert_eq!(OutputFormat::from_name("LIST"), Some(OutputFormat::List));
+    }
+
+    #[test]
+    fn test_search_error_messages() {
+        let connection = search_error(SearchError::Connection(
+            "Unable to connect to SearXNG server at http://localhost:8080: connection refused"
+                .into(),
+        ));
+        assert_eq!(connection.kind, ErrorKind::Connection);
+        assert!(
+            connection
+                .message
+                .starts_with("Search failed: Unable to connect")
+        );
+        assert!(connection.message.contains("Check that SEARXNG_BASE_URL"));
+
+        let parse = search_error(SearchError::Parse(
+            "Failed to parse response: expected value at line 1 column 1".into(),
+        ));
+        assert_eq!(parse.kind, ErrorKind::Parse);
+        assert!(parse.message.contains("expected value at line 1"));
+        assert!(parse.message.contains("JSON format enabled"));
+        assert!(!parse.message.contains("running SearXNG instance"));
+
+        let status = search_error(SearchError::HttpStatus(502, "HTTP Error: 502".into()));
+        assert_eq!(status.kind, ErrorKind::HttpStatus);
+        assert_eq!(status.message, "Search failed: HTTP Error: 502");
+    }
+
+    #[test]
+    fn test_extra_params_arg() {
+        let args = json!({ "extra_params": { "theme": "simple", " image_proxy ": "true" } });
+        assert_eq!(
+            extra_params_arg(args.as_object().unwrap()).unwrap(),
+            BTreeMap::from([
+                ("image_proxy".to_string(), "true".to_string()),
+                ("theme".to_string(), "simple".to_string()),
+            ])
+        );
+        assert!(extra_params_arg(&Map::new()).unwrap().is_empty());
+
+        for bad in [
+            json!({ "extra_params": { "pageno": "2" } }),
+            json!({ "extra_params": { "Q": "other query" } }),
+            json!({ "extra_params": { "theme": 1 } }),
+            json!({ "extra_params": { "": "x" } }),
+            json!({ "extra_params": "theme=simple" }),
+        ] {
+            assert_eq!(
+                extra_params_arg(bad.as_object().unwrap()).unwrap_err().kind,
+                ErrorKind::InvalidArgument
+            );
+        }
+        let error = extra_params_arg(
+            json!({ "extra_params": { "engines": "bing" } })
+                .as_object()
+                .unwrap(),
+        )
+        .unwrap_err();
+        assert!(error.message.contains("cannot set engines"));
+    }
+
+    #[test]
+    fn test_list_arg_accepts_strings_and_arrays() {
+        let args = json!({
+            "string": "google, bing ,,duckduckgo",
+            "array": ["duckduckgo", " wikipedia "],
+            "mixed": [" news ,it", "  ", "science"],
+            "empty": [],
+            "blank": " , ",
+            "bad_item": [1],
+            "bad_type": 3,
+        });
+        let args = args.as_object().unwrap();
+
+        assert_eq!(
+            list_arg(args, "string").unwrap(),
+            vec!["google", "bing", "duckduckgo"]
+        );
+        assert_eq!(
+            list_arg(args, "array").unwrap(),
+            vec!["duckduckgo", "wikipedia"]
+        );
+        assert_eq!(
+            list_arg(args, "mixed").unwrap(),
+            vec!["news", "it", "science"]
+        );
+        assert!(list_arg(args, "missing").unwrap().is_empty());
+        assert_eq!(joined_list_arg(args, "empty").unwrap(), None);
+        assert_eq!(joined_list_arg(args, "blank").unwrap(), None);
+        assert_eq!(
+            joined_list_arg(args, "array").unwrap().as_deref(),
+            Some("duckduckgo,wikipedia")
+        );
+        assert_eq!(
+            list_arg(args, "bad_item").unwrap_err().kind,
+            ErrorKind::InvalidArgument
+        );
+        assert_eq!(
+            list_arg(args, "bad_type").unwrap_err().kind,
+            ErrorKind::InvalidArgument
+        );
+    }
+}
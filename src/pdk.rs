@@ -1,5 +1,6 @@
 #![allow(non_snake_case)]
 #![allow(unused_macros)]
+#![allow(dead_code)]
 use extism_pdk::*;
 
 #[allow(unused)]
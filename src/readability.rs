@@ -0,0 +1,393 @@
+use ego_tree::{NodeId, NodeRef};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+/// Class/id fragments that mark boilerplate containers
+const UNLIKELY_CANDIDATES: &str = r"(?i)-ad-|ai2html|banner|breadcrumbs|combx|comment|community|consent|cookie|cover-wrap|disqus|extra|footer|gdpr|header|newsletter|legends|menu|related|remark|replies|rss|shoutbox|sidebar|skyscraper|social|sponsor|supplemental|ad-break|agegate|pagination|pager|popup|yom-remote";
+
+/// Class/id fragments that rescue an otherwise unlikely container
+const MAYBE_CANDIDATE: &str = r"(?i)and|article|body|column|content|main|shadow";
+
+const POSITIVE_WEIGHT: &str =
+    r"(?i)article|body|content|entry|hentry|h-entry|main|page|pagination|post|text|blog|story";
+
+const NEGATIVE_WEIGHT: &str = r"(?i)-ad-|hidden|^hid$| hid$| hid |^hid |banner|combx|comment|com-|contact|footer|gdpr|masthead|media|meta|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|sponsor|shopping|tags|widget";
+
+/// Elements whose text is scored as article content
+const SCORED_TAGS: &str = "p, pre, td, blockquote";
+
+/// Paragraphs shorter than this are ignored when scoring
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+
+/// Elements a page marks as its main content, trusted before any scoring
+const MAIN_LANDMARKS: &str = "article, main, [role=main]";
+
+/// Text a main landmark needs, in characters, to be trusted; shorter ones are often teasers
+const MIN_LANDMARK_TEXT: usize = 140;
+
+/// Page chrome dropped wherever it appears in the extracted content
+const CHROME_TAGS: &[&str] = &["nav", "aside", "footer"];
+
+/// Elements that never have a closing tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Candidate patterns, compiled once rather than on every extraction
+static PATTERNS: LazyLock<Patterns> = LazyLock::new(Patterns::new);
+
+struct Patterns {
+    unlikely: Regex,
+    maybe: Regex,
+    positive: Regex,
+    negative: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        Self {
+            unlikely: Regex::new(UNLIKELY_CANDIDATES).unwrap(),
+            maybe: Regex::new(MAYBE_CANDIDATE).unwrap(),
+            positive: Regex::new(POSITIVE_WEIGHT).unwrap(),
+            negative: Regex::new(NEGATIVE_WEIGHT).unwrap(),
+        }
+    }
+
+    /// Whether an element looks like navigation, ads or other page chrome
+    fn is_unlikely(&self, element: &ElementRef) -> bool {
+        if matches!(element.value().name(), "body" | "a" | "html") {
+            return false;
+        }
+        let match_string = class_and_id(element);
+        self.unlikely.is_match(&match_string) && !self.maybe.is_match(&match_string)
+    }
+
+    /// Score bonus or penalty derived from class and id names
+    fn class_weight(&self, element: &ElementRef) -> f64 {
+        let mut weight = 0.0;
+        for value in [element.value().attr("class"), element.value().attr("id")]
+            .into_iter()
+            .flatten()
+        {
+            if self.negative.is_match(value) {
+                weight -= 25.0;
+            }
+            if self.positive.is_match(value) {
+                weight += 25.0;
+            }
+        }
+        weight
+    }
+}
+
+fn class_and_id(element: &ElementRef) -> String {
+    format!(
+        "{} {}",
+        element.value().attr("class").unwrap_or_default(),
+        element.value().attr("id").unwrap_or_default()
+    )
+}
+
+/// Starting score of a candidate container based on its tag
+fn tag_weight(name: &str) -> f64 {
+    match name {
+        "div" | "article" | "main" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    }
+}
+
+fn text_length(element: &ElementRef) -> usize {
+    element.text().map(|t| t.trim().chars().count()).sum()
+}
+
+/// Fraction of an element's text that sits inside links
+fn link_density(element: &ElementRef) -> f64 {
+    let total = text_length(element);
+    if total == 0 {
+        return 0.0;
+    }
+    let link_selector = Selector::parse("a").unwrap();
+    let link_length: usize = element
+        .select(&link_selector)
+        .map(|a| text_length(&a))
+        .sum();
+    link_length as f64 / total as f64
+}
+
+/// Whether the element or one of its ancestors is an unlikely candidate
+fn inside_unlikely(element: &ElementRef, patterns: &Patterns) -> bool {
+    std::iter::once(**element)
+        .chain(element.ancestors())
+        .filter_map(ElementRef::wrap)
+        .any(|e| patterns.is_unlikely(&e))
+}
+
+/// The page's own main landmark with the most text, if it has enough to be the article
+fn main_landmark(document: &Html) -> Option<ElementRef<'_>> {
+    let landmarks = Selector::parse(MAIN_LANDMARKS).unwrap();
+    document
+        .select(&landmarks)
+        .map(|element| (element, text_length(&element)))
+        .filter(|(_, length)| *length >= MIN_LANDMARK_TEXT)
+        .max_by_key(|(_, length)| *length)
+        .map(|(element, _)| element)
+}
+
+/// Whether an element is navigation, a sidebar or a footer by its tag or ARIA role.
+/// A `<header>` counts too, unless it holds the article's `<h1>`.
+fn is_chrome(element: &ElementRef) -> bool {
+    let name = element.value().name();
+    if CHROME_TAGS.contains(&name) {
+        return true;
+    }
+    if name == "header" {
+        let h1 = Selector::parse("h1").unwrap();
+        return element.select(&h1).next().is_none();
+    }
+    matches!(
+        element.value().attr("role"),
+        Some("navigation" | "banner" | "complementary" | "contentinfo")
+    )
+}
+
+/// Pick the container holding the bulk of the page's article text
+fn top_candidate<'a>(document: &'a Html, patterns: &Patterns) -> Option<ElementRef<'a>> {
+    let scored = Selector::parse(SCORED_TAGS).unwrap();
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+
+    for paragraph in document.select(&scored) {
+        if inside_unlikely(&paragraph, patterns) {
+            continue;
+        }
+
+        let text: String = paragraph.text().collect();
+        let length = text.trim().chars().count();
+        if length < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+
+        let content_score =
+            1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+
+        let ancestors = paragraph.ancestors().filter_map(ElementRef::wrap).take(2);
+        for (level, ancestor) in ancestors.enumerate() {
+            let entry = scores.entry(ancestor.id()).or_insert_with(|| {
+                tag_weight(ancestor.value().name()) + patterns.class_weight(&ancestor)
+            });
+            *entry += if level == 0 {
+                content_score
+            } else {
+                content_score / 2.0
+            };
+        }
+    }
+
+    scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let element = ElementRef::wrap(document.tree.get(id)?)?;
+            Some((element, score * (1.0 - link_density(&element))))
+        })
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(element, _)| element)
+}
+
+fn escape_text(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// Serialize a node back to HTML, leaving out scripts, styles and page chrome
+fn render(node: NodeRef<Node>, patterns: &Patterns, out: &mut String) {
+    match node.value() {
+        Node::Text(text) => escape_text(text, out),
+        Node::Element(element) => {
+            let name = element.name();
+            if matches!(name, "script" | "style" | "noscript") {
+                return;
+            }
+            if let Some(element_ref) = ElementRef::wrap(node)
+                && (patterns.is_unlikely(&element_ref) || is_chrome(&element_ref))
+            {
+                return;
+            }
+
+            out.push('<');
+            out.push_str(name);
+            for (attr, value) in element.attrs() {
+                out.push(' ');
+                out.push_str(attr);
+                out.push_str("=\"");
+                out.push_str(&value.replace('"', "&quot;"));
+                out.push('"');
+            }
+            out.push('>');
+
+            if VOID_ELEMENTS.contains(&name) {
+                return;
+            }
+
+            for child in node.children() {
+                render(child, patterns, out);
+            }
+            out.push_str("</");
+            out.push_str(name);
+            out.push('>');
+        }
+        _ => {
+            for child in node.children() {
+                render(child, patterns, out);
+            }
+        }
+    }
+}
+
+/// Extract the main article content of a page as HTML.
+///
+/// An `<article>`, `<main>` or `[role=main]` element with enough text is taken as is.
+/// Otherwise paragraph-like elements are scored by length and comma count, the score is
+/// propagated to their parent and grandparent, and the container with the best
+/// score (discounted by link density) wins. Falls back to the whole `<body>`
+/// when nothing on the page looks like prose. Navigation, sidebars, footers and
+/// boilerplate classes are left out of whatever is chosen.
+pub fn extract_main_content(html: &str) -> String {
+    let document = Html::parse_document(html);
+
+    let root = match main_landmark(&document).or_else(|| top_candidate(&document, &PATTERNS)) {
+        Some(candidate) => candidate,
+        None => {
+            let body = Selector::parse("body").unwrap();
+            match document.select(&body).next() {
+                Some(body) => body,
+                None => return html.to_string(),
+            }
+        }
+    };
+
+    let mut out = String::new();
+    render(*root, &PATTERNS, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NEWS_PAGE: &str = r#"
+    <html>
+    <head><title>Local news</title></head>
+    <body>
+    <div class="header"><a href="/">Home</a> <a href="/world">World</a> <a href="/sport">Sport</a></div>
+    <div class="sidebar">
+      <ul><li><a href="/a">Most read: celebrity gossip of the day</a></li><li><a href="/b">Trending: a video of a cat on a skateboard</a></li></ul>
+    </div>
+    <div class="article-body">
+      <h1>Bridge reopens after repairs</h1>
+      <p>The city bridge reopened on Monday, after six months of repairs, to the relief of commuters, cyclists, and local shops.</p>
+      <p>Engineers replaced the deck, repainted the steel, and installed new lighting, according to the transport department.</p>
+      <p>Officials said traffic should return to normal levels within a week, although some lanes remain restricted.</p>
+    </div>
+    <div class="footer"><p>Copyright 2024 Example News Corporation, all rights reserved worldwide.</p></div>
+    </body>
+    </html>
+    "#;
+
+    #[test]
+    fn test_extract_main_content_keeps_article() {
+        let raw = html2md::parse_html(NEWS_PAGE);
+        assert!(raw.contains("celebrity gossip"));
+        assert!(raw.contains("Copyright 2024"));
+
+        let markdown = html2md::parse_html(&extract_main_content(NEWS_PAGE));
+        assert!(markdown.contains("Bridge reopens after repairs"));
+        assert!(markdown.contains("The city bridge reopened on Monday"));
+        assert!(markdown.contains("some lanes remain restricted"));
+        assert!(!markdown.contains("celebrity gossip"));
+        assert!(!markdown.contains("Copyright 2024"));
+        assert!(!markdown.contains("Sport"));
+    }
+
+    const NEWS_ARTICLE_PAGE: &str = r#"
+    <html>
+    <body>
+    <div class="cookie-consent"><p>We use cookies to improve your experience, accept them all to continue reading.</p></div>
+    <nav><a href="/">Home</a> <a href="/politics">Politics</a></nav>
+    <article>
+      <header><h1>Council approves new park</h1><p>By A. Reporter</p></header>
+      <p>The council voted on Tuesday to turn the disused rail yard into a park, ending years of debate.</p>
+      <aside><p>Read more: ten parks to visit this summer, ranked by our readers and editors.</p></aside>
+      <p>Construction starts next spring, and the first section should open to the public by autumn.</p>
+      <footer><p>Share this article on social media with your friends and family members.</p></footer>
+    </article>
+    <article><p>Teaser</p></article>
+    </body>
+    </html>
+    "#;
+
+    const DOCS_PAGE: &str = r#"
+    <html>
+    <body>
+    <header><a href="/">Docs home</a> <input placeholder="Search the docs"></header>
+    <div role="navigation"><ul><li><a href="/install">Installation</a></li><li><a href="/config">Configuration reference</a></li></ul></div>
+    <div role="main">
+      <h1>Configuration</h1>
+      <p>Settings are read from the plugin configuration when the first tool call is made, and cached afterwards.</p>
+      <pre><code>SEARXNG_BASE_URL=http://localhost:8080</code></pre>
+      <p>Unknown keys are ignored, so a typo only shows up as a setting that keeps its default value.</p>
+    </div>
+    <footer><p>Edit this page on the repository hosting service. Last updated in 2024.</p></footer>
+    </body>
+    </html>
+    "#;
+
+    #[test]
+    fn test_extract_main_content_prefers_article_landmark() {
+        let raw = html2md::parse_html(NEWS_ARTICLE_PAGE);
+        assert!(raw.contains("We use cookies"));
+        assert!(raw.contains("ten parks to visit"));
+
+        let markdown = html2md::parse_html(&extract_main_content(NEWS_ARTICLE_PAGE));
+        assert!(markdown.contains("Council approves new park"));
+        assert!(markdown.contains("disused rail yard"));
+        assert!(markdown.contains("Construction starts next spring"));
+        assert!(!markdown.contains("We use cookies"));
+        assert!(!markdown.contains("Politics"));
+        assert!(!markdown.contains("ten parks to visit"));
+        assert!(!markdown.contains("Share this article"));
+        assert!(!markdown.contains("Teaser"));
+    }
+
+    #[test]
+    fn test_extract_main_content_docs_page() {
+        let raw = html2md::parse_html(DOCS_PAGE);
+        assert!(raw.contains("Configuration reference"));
+        assert!(raw.contains("Edit this page"));
+
+        let markdown = html2md::parse_html(&extract_main_content(DOCS_PAGE));
+        assert!(markdown.contains("Settings are read from the plugin configuration"));
+        assert!(markdown.contains("SEARXNG_BASE_URL=http://localhost:8080"));
+        assert!(!markdown.contains("Configuration reference"));
+        assert!(!markdown.contains("Docs home"));
+        assert!(!markdown.contains("Edit this page"));
+    }
+
+    #[test]
+    fn test_extract_main_content_falls_back_to_body() {
+        let html = "<html><body><span>Short</span> <b>page</b></body></html>";
+        let markdown = html2md::parse_html(&extract_main_content(html));
+        assert!(markdown.contains("Short"));
+        assert!(markdown.contains("page"));
+    }
+}
@@ -0,0 +1,107 @@
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Longest host-supplied request ID kept, in characters
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+thread_local! {
+    /// ID of the tool call being handled. Plugin calls never overlap, so one slot is enough
+    static REQUEST_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Start tracing a tool call under the host's `request_id`, or a fresh random one
+pub fn begin_request(supplied: Option<&str>) {
+    let id = supplied
+        .and_then(sanitize_request_id)
+        .unwrap_or_else(random_request_id);
+    REQUEST_ID.with(|slot| *slot.borrow_mut() = Some(id));
+}
+
+/// Prefix for log lines of the current tool call, empty outside of one
+pub fn log_prefix() -> String {
+    REQUEST_ID.with(|slot| match slot.borrow().as_deref() {
+        Some(id) => format!("[{}] ", id),
+        None => String::new(),
+    })
+}
+
+/// Keep a host-supplied ID only if it is short and safe to put in a log line
+fn sanitize_request_id(id: &str) -> Option<String> {
+    let id = id.trim();
+    let valid = !id.is_empty()
+        && id.chars().count() <= MAX_REQUEST_ID_LEN
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    valid.then(|| id.to_string())
+}
+
+/// Eight hex digits, seeded from the randomness behind `HashMap`
+fn random_request_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(crate::searxng::unix_now());
+    format!("{:08x}", hasher.finish() as u32)
+}
+
+/// `extism_pdk::info!` with the current request ID prepended
+#[cfg(not(test))]
+macro_rules! traced_info {
+    ($($arg:tt)+) => {
+        extism_pdk::info!("{}{}", $crate::trace::log_prefix(), format_args!($($arg)+))
+    };
+}
+
+/// `extism_pdk::warn!` with the current request ID prepended
+#[cfg(not(test))]
+macro_rules! traced_warn {
+    ($($arg:tt)+) => {
+        extism_pdk::warn!("{}{}", $crate::trace::log_prefix(), format_args!($($arg)+))
+    };
+}
+
+/// Unit tests have no host to log to, so lines go to stderr
+#[cfg(test)]
+macro_rules! traced_info {
+    ($($arg:tt)+) => {
+        eprintln!("{}{}", $crate::trace::log_prefix(), format_args!($($arg)+))
+    };
+}
+
+#[cfg(test)]
+macro_rules! traced_warn {
+    ($($arg:tt)+) => {
+        eprintln!("{}{}", $crate::trace::log_prefix(), format_args!($($arg)+))
+    };
+}
+
+// Renamed on export: a `warn` macro cannot be re-exported directly past the `#[warn]` attribute
+pub(crate) use {traced_info as info, traced_warn as warn};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_request_id() {
+        assert_eq!(
+            sanitize_request_id(" run-42:step_3 "),
+            Some("run-42:step_3".into())
+        );
+        assert_eq!(sanitize_request_id(""), None);
+        assert_eq!(sanitize_request_id("has space"), None);
+        assert_eq!(sanitize_request_id("line\nbreak"), None);
+        assert_eq!(sanitize_request_id(&"a".repeat(65)), None);
+    }
+
+    #[test]
+    fn test_begin_request_sets_log_prefix() {
+        begin_request(Some("host-id"));
+        assert_eq!(log_prefix(), "[host-id] ");
+
+        begin_request(Some("bad id"));
+        let prefix = log_prefix();
+        assert_eq!(prefix.len(), "[12345678] ".len());
+        assert_ne!(prefix, "[host-id] ");
+    }
+}